@@ -38,15 +38,32 @@ extern crate srml_session as session;
 use democracy::{Approved, VoteThreshold};
 
 use rstd::prelude::*;
-use system::ensure_signed;
+use primitives::{H256, H512};
+use codec::Encode;
+use system::{ensure_signed, ensure_root};
 use runtime_support::{StorageValue, StorageMap};
+use runtime_support::storage::{self, generator};
 use runtime_support::dispatch::Result;
-use runtime_primitives::traits::{Zero, Hash};
+use runtime_primitives::traits::{Zero, One, Hash, As, IntegerSquareRoot, CheckedAdd, Bounded};
+
+/// Version of the bridge record and message layouts. Relayers and counterpart
+/// chain contracts should check this before interacting with the module, and it
+/// must be bumped whenever a record or message layout changes.
+pub const BRIDGE_VERSION: u32 = 1;
+
+/// Maximum length in bytes of the metadata an authority may publish.
+pub const MAX_AUTHORITY_METADATA_LEN: usize = 256;
+
+/// Maximum number of withdraws an authority may sign in one `confirm_withdraws_batch` call.
+pub const MAX_WITHDRAW_BATCH_SIZE: usize = 64;
 
 /// Record indices.
 pub type DepositIndex = u32;
 pub type WithdrawIndex = u32;
 
+/// Index of a transfer's log within its source chain transaction.
+pub type LogIndex = u32;
+
 pub trait Trait: balances::Trait + session::Trait {
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -54,81 +71,191 @@ pub trait Trait: balances::Trait + session::Trait {
 
 pub type LinkedProof = Vec<u8>;
 
+/// A request to mint native tokens for tokens deposited on an eligible blockchain.
+///
+/// Fields are encoded in the same order as the tuple records this replaced, so
+/// records written before the change decode unchanged.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct DepositRecord<AccountId, Balance> {
+    /// Position of the deposit in the order requests were made
+    pub index: DepositIndex,
+    /// Account to credit once the deposit is approved
+    pub target: AccountId,
+    /// Amount to credit
+    pub quantity: Balance,
+    /// Bridge authorities who have signed off on the deposit
+    pub signers: Vec<AccountId>,
+    /// Whether the deposit has been approved and credited
+    pub completed: bool,
+}
+
+/// A compact record of a finalized deposit, kept for the target to reference.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct DepositReceipt<Hash, Balance, BlockNumber> {
+    /// Hash of the deposit transaction on the eligible blockchain
+    pub transaction_hash: Hash,
+    /// Index of the deposit log within that transaction
+    pub log_index: LogIndex,
+    /// Amount credited
+    pub quantity: Balance,
+    /// Block in which the deposit was finalized
+    pub finalized_at: BlockNumber,
+    /// Blocks between the deposit's creation and its finalization, if its creation
+    /// block is known
+    pub latency: Option<BlockNumber>,
+}
+
+/// A balance credited by governance outside the deposit flow, kept for audit.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct ManualIntervention<AccountId, Balance, BlockNumber> {
+    /// Account credited
+    pub target: AccountId,
+    /// Amount credited
+    pub quantity: Balance,
+    /// Governance's stated reason for the credit
+    pub reason: Vec<u8>,
+    /// Block in which the credit was made
+    pub block: BlockNumber,
+}
+
+/// The projected effect of submitting a deposit, as returned by `simulate_deposit`.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum DepositSimOutcome {
+    /// A new deposit record would be created
+    Create,
+    /// The sender would be added as a signer without finalizing the deposit
+    Append,
+    /// The sender's signature would finalize and credit the deposit
+    Finalize,
+    /// The sender's signature would approve the deposit, but it cannot be credited and
+    /// would be rejected
+    Reject,
+    /// The submission would succeed without changing anything, as a retry of the
+    /// sender's earlier signature
+    Unchanged,
+    /// The submission would fail with the given error
+    Error(&'static str),
+}
+
+/// A request to burn native tokens in exchange for tokens on an eligible blockchain.
+///
+/// Encoded identically to the tuple records this replaced.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct WithdrawRecord<AccountId, Balance> {
+    /// Position of the withdraw in the order requests were made
+    pub index: WithdrawIndex,
+    /// Account whose balance is withdrawn
+    pub target: AccountId,
+    /// Amount to withdraw
+    pub quantity: Balance,
+    /// Bridge authorities who have signed off, with their signed cross-chain transaction
+    pub signers: Vec<(AccountId, LinkedProof)>,
+    /// Whether the withdraw has been approved and debited
+    pub completed: bool,
+}
+
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         fn deposit_event() = default;
 
+        fn on_finalise(n: T::BlockNumber) {
+            Self::migrate_deposit_keys();
+            if let Some((activate_at, next_set)) = Self::scheduled_authorities() {
+                if n >= activate_at {
+                    <ScheduledAuthorities<T>>::kill();
+                    <GovernanceAuthorities<T>>::put(true);
+                    Self::set_authorities(next_set);
+                    Self::deposit_event(RawEvent::ScheduledRotationApplied(n, Self::authority_set_id()));
+                }
+            }
+            Self::expire_deposit_intents(n);
+            Self::release_reviewed_deposits(n);
+            Self::expire_withdraws(n);
+        }
+
         /// The deposit function should always succeed (in order) a deposit transaction
         /// on the eligible blockchain that has an established two-way peg with Edgeware.
         /// This function can be triggered by the depositor or any bridge authority that
-        /// sees the transaction first.
-        pub fn deposit(origin, target: T::AccountId, transaction_hash: T::Hash, quantity: T::Balance) -> Result {
+        /// sees the transaction first, unless user intent is required before attestation.
+        ///
+        /// A source transaction can carry several transfers, so each deposit is identified
+        /// by the transaction hash together with the index of the transfer's log.
+        pub fn deposit(origin, target: T::AccountId, transaction_hash: T::Hash, log_index: LogIndex, quantity: T::Balance) -> Result {
             let _sender = ensure_signed(origin)?;
-            
-            // Match on deposit records by the respective transaction hash on the eligible blockchain
-            match <DepositOf<T>>::get(transaction_hash) {
+            Self::ensure_deposits_active()?;
+            let key = (transaction_hash, log_index);
+
+            // Match on deposit records by the respective transaction hash and log index on the eligible blockchain
+            match <DepositOf<T>>::get(key) {
                 Some(_) => { return Err("Deposit should not exist")},
                 None => {
                     // If sender is a bridge authority add them to the set of signers
                     let mut signers = vec![];
                     if <Authorities<T>>::get().iter().any(|a| a == &_sender) {
-                        signers.push(_sender);
+                        // Authorities may only attest to deposits the user has registered
+                        ensure!(!Self::require_intent_before_attestation(), "Deposit intent required");
+                        // Authorities outside the deposit's committee do not count as signers
+                        if Self::in_deposit_committee(&key, &_sender) {
+                            signers.push(_sender.clone());
+                            <DepositSignedAt<T>>::insert((key, _sender.clone()), <system::Module<T>>::block_number());
+                        }
                     }
 
                     // Create new deposit record
                     let mut deposits = <Deposits<T>>::get();
-                    deposits.push(transaction_hash);
+                    deposits.push(key);
                     <Deposits<T>>::put(deposits);
 
                     // Insert deposit record and send event
                     let index = Self::deposit_count();
                     <DepositCount<T>>::mutate(|i| *i += 1);
-                    <DepositOf<T>>::insert(transaction_hash, (index, target.clone(), quantity, signers, false));
-                    Self::deposit_event(RawEvent::Deposit(target, transaction_hash, quantity));
+                    <DepositCreatedAt<T>>::insert(key, <system::Module<T>>::block_number());
+                    let expiry = Self::deposit_intent_expiry();
+                    if signers.is_empty() && !expiry.is_zero() {
+                        <DepositIntentsExpiringAt<T>>::mutate(<system::Module<T>>::block_number() + expiry, |keys| keys.push(key));
+                    }
+                    <DepositOf<T>>::insert(key, DepositRecord {
+                        index: index,
+                        target: target.clone(),
+                        quantity: quantity,
+                        signers: signers,
+                        completed: false,
+                    });
+                    Self::deposit_event(RawEvent::Deposit(target, transaction_hash, log_index, quantity));
                 },
             }
 
+            Self::note_relayer_submission(&_sender);
             Ok(())
         }
 
         /// The sign_deposit function should compile intentions (from sending tx) and
         /// check if a deposit proposal ever passes with each new valid signer.
-        pub fn sign_deposit(origin, target: T::AccountId, transaction_hash: T::Hash, quantity: T::Balance) -> Result {
+        pub fn sign_deposit(origin, target: T::AccountId, transaction_hash: T::Hash, log_index: LogIndex, quantity: T::Balance) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_deposits_active()?;
+            let key = (transaction_hash, log_index);
 
-            match <DepositOf<T>>::get(transaction_hash) {
-                Some((inx, tgt, qty, signers, completed)) => {
-                    // Ensure all parameters match for safety
-                    ensure!(tgt == target.clone(), "Accounts do not match");
-                    ensure!(qty == quantity, "Quantities don't match");
-                    ensure!(!completed, "Transaction already completed");
-                    // Ensure sender is a bridge authority
-                    ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
-                    // Ensure senders can't sign twice
-                    ensure!(!signers.iter().any(|id| id == &_sender), "Invalid duplicate signings");
-                    // Add record update with new signer
-                    let mut new_signers = signers.clone();
-                    new_signers.push(_sender.clone());
-
-                    // Check if we have reached enough signers for the deposit
-                    // TODO: Ensure that checking balances is sufficient vs. finding explicit stake amounts
-                    let stake_sum = new_signers.iter()
-                        .map(|s| <balances::Module<T>>::total_balance(s))
-                        .fold(Zero::zero(), |a,b| a + b);
-
-                    // Check if we approve the proposal, if so, mark approved
-                    let total_issuance = <balances::Module<T>>::total_issuance();
-                    if VoteThreshold::SuperMajorityApprove.approved(stake_sum, total_issuance - stake_sum, total_issuance, total_issuance) {
-                        <balances::Module<T>>::increase_free_balance_creating(&tgt, qty);
-                        <DepositOf<T>>::insert(transaction_hash, (inx, tgt.clone(), qty, new_signers.clone(), true));
-                        // TODO: fire event
-                    } else {
-                        <DepositOf<T>>::insert(transaction_hash, (inx, tgt.clone(), qty, new_signers.clone(), false));
-                    }
-                },
-                None => { return Err("Invalid transaction hash") },
+            // A retried attestation is a no-op rather than a failed extrinsic
+            if Self::is_idempotent_retry(&_sender, &target, &key, quantity) {
+                return Ok(());
+            }
+
+            let (mut record, approved) = Self::prepare_sign_deposit(&_sender, &target, &key, quantity)?;
+
+            // If we approve the proposal, credit the target and mark approved
+            if approved {
+                Self::approve_deposit(&key, &mut record)?;
             }
+            <DepositSignedAt<T>>::insert((key, _sender.clone()), <system::Module<T>>::block_number());
+            <DepositOf<T>>::insert(key, record);
 
+            Self::note_relayer_submission(&_sender);
             Ok(())
         }
 
@@ -137,14 +264,30 @@ decl_module! {
         /// eligible blockchain that has an established two-way peg with Edgeware. This
         /// function should only be called by a token holder interested in transferring
         /// native Edgeware tokens with Edgeware-compliant, non-native tokens like ERC20.
-        pub fn withdraw(origin, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>) -> Result {
+        ///
+        /// If the withdraw later fails, the amount is refunded to `refund_to`, or to the
+        /// sender when it is `None`. A withdraw given an `expire_at` block is cancelled
+        /// at the end of that block if it has not been approved by then.
+        pub fn withdraw(origin, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>, refund_to: Option<T::AccountId>, expire_at: Option<T::BlockNumber>) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_withdraws_active()?;
+            let expire_at = Self::clamp_withdraw_expiry(expire_at)?;
+
+            // Ensure the quantity is within the configured bounds
+            ensure!(quantity >= Self::min_withdraw(), "Withdraw below minimum");
+            let max_withdraw = Self::max_withdraw();
+            ensure!(max_withdraw.is_zero() || quantity <= max_withdraw, "Withdraw above maximum");
+
+            // Ensure the sender has not used up their withdraws for the current window
+            let window = Self::next_withdraw_in_window(&_sender)?;
 
             let mut nonce = Self::withdraw_nonce_of(_sender.clone());
             let key = T::Hashing::hash_of(&(nonce, _sender.clone(), quantity));
 
+            // Completed records are kept for replay protection, so an existing key means the
+            // nonce was reset and the record must not be overwritten
             match <WithdrawOf<T>>::get(key) {
-                Some(_) => { return Err("Withdraw already exists")},
+                Some(_) => { return Err("Withdraw hash collision")},
                 None => {
                     // If sender is a bridge authority add them to the set of signers
                     let mut signers = vec![];
@@ -163,8 +306,26 @@ decl_module! {
                     // Insert withdraw record and send event
                     let index = Self::withdraw_count();
                     <WithdrawCount<T>>::mutate(|i| *i += 1);
-                    <WithdrawOf<T>>::insert(key, (index, _sender.clone(), quantity, signers, false));
-                    Self::deposit_event(RawEvent::Withdraw(_sender.clone(), quantity));
+                    <WithdrawOf<T>>::insert(key, WithdrawRecord {
+                        index: index,
+                        target: _sender.clone(),
+                        quantity: quantity,
+                        signers: signers,
+                        completed: false,
+                    });
+                    <WithdrawsInWindow<T>>::insert(_sender.clone(), window);
+                    <WithdrawReserved<T>>::mutate(&_sender, |reserved| {
+                        *reserved = reserved.checked_add(&quantity).unwrap_or_else(T::Balance::max_value);
+                    });
+                    if let Some(refund_to) = refund_to {
+                        <WithdrawRefundTo<T>>::insert(key, refund_to);
+                    }
+                    <WithdrawCountedInWindow<T>>::insert(key, window.0);
+                    if let Some(expire_at) = expire_at {
+                        <WithdrawsExpiringAt<T>>::mutate(expire_at, |hashes| hashes.push(key));
+                        <WithdrawExpiresAt<T>>::insert(key, expire_at);
+                    }
+                    Self::deposit_event(RawEvent::Withdraw(_sender.clone(), quantity, Self::next_message_nonce()));
                 },
             }
 
@@ -177,43 +338,535 @@ decl_module! {
         /// check if a withdraw proposal ever passes with each new valid signer.
         pub fn sign_withdraw(origin, target: T::AccountId, record_hash: T::Hash, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_withdraws_active()?;
+
+            Self::apply_withdraw_signature(&_sender, target, record_hash, quantity, signed_cross_chain_tx)?;
+
+            Self::note_relayer_submission(&_sender);
+            Ok(())
+        }
+
+        /// Sign several withdraws at once, each given as its target, record hash, quantity
+        /// and signed cross-chain transaction as for `sign_withdraw`. An item that cannot
+        /// be signed is skipped with a `WithdrawSignatureFailed` event instead of failing
+        /// the whole batch. At most `MAX_WITHDRAW_BATCH_SIZE` items are accepted.
+        pub fn confirm_withdraws_batch(origin, items: Vec<(T::AccountId, T::Hash, T::Balance, Vec<u8>)>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_withdraws_active()?;
+            ensure!(!items.is_empty(), "Invalid empty batch");
+            ensure!(items.len() <= MAX_WITHDRAW_BATCH_SIZE, "Batch too large");
+            ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
+
+            for (target, record_hash, quantity, signed_cross_chain_tx) in items {
+                if let Err(err) = Self::apply_withdraw_signature(&_sender, target, record_hash, quantity, signed_cross_chain_tx) {
+                    Self::deposit_event(RawEvent::WithdrawSignatureFailed(_sender.clone(), record_hash, err.as_bytes().to_vec()));
+                }
+            }
+
+            Self::note_relayer_submission(&_sender);
+            Ok(())
+        }
+
+        /// Attest that the cross-chain transaction for a withdraw failed on the eligible
+        /// blockchain. Once enough authorities agree, the withdraw is cancelled and any
+        /// amount already withdrawn is minted back to the account.
+        pub fn attest_withdraw_failure(origin, record_hash: T::Hash) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_withdraws_active()?;
 
-            match <WithdrawOf<T>>::get(record_hash) {
-                Some((inx, tgt, qty, signers, completed)) => {
-                    // Ensure all parameters match for safety
-                    ensure!(tgt == target.clone(), "Accounts do not match");
-                    ensure!(qty == quantity, "Quantities don't match");
-                    ensure!(!completed, "Transaction already completed");
-                    // Ensure sender is a bridge authority if record exists
-                    ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
-                    // Ensure senders can't sign twice
-                    ensure!(!signers.iter().any(|s| s.0 == _sender), "Invalid duplicate signings");
-                    // Add record update with new signer
-                    let mut new_signers = signers;
-                    new_signers.push((_sender, signed_cross_chain_tx));
-
-                    // Check if we have reached enough signers for the withdrawal
-                    // TODO: Ensure that checking balances is sufficient vs. finding explicit stake amounts
-                    let stake_sum = new_signers.iter()
-                        .map(|s| <balances::Module<T>>::total_balance(&s.0))
-                        .fold(Zero::zero(), |a,b| a + b);
-
-                    // Check if we approve the proposal
-                    let total_issuance = <balances::Module<T>>::total_issuance();
-                    if VoteThreshold::SuperMajorityApprove.approved(stake_sum, total_issuance - stake_sum, total_issuance, total_issuance) {
-                        match <balances::Module<T>>::decrease_free_balance(&tgt, qty) {
-                            Ok(_) => {
-                                // TODO: do we still mark completed on error? or store a "failed" tx?
-                                <WithdrawOf<T>>::insert(record_hash, (inx, tgt.clone(), qty, new_signers.clone(), true));
-                                // TODO: fire event
-                            },
-                            Err(err) => { return Err(err); } // TODO test this?
-                        };
-                    } else {
-                        <WithdrawOf<T>>::insert(record_hash, (inx, tgt.clone(), qty, new_signers.clone(), false));
+            let record = match <WithdrawOf<T>>::get(record_hash) {
+                Some(record) => record,
+                None => { return Err("Invalid record hash") },
+            };
+            ensure!(!Self::withdraw_failed(record_hash), "Withdraw already failed");
+            ensure!(!Self::withdraw_expired(record_hash), "Withdraw expired");
+            // Ensure sender is a bridge authority who has not attested yet
+            ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
+            let mut attesters = Self::withdraw_failure_attesters(record_hash);
+            ensure!(!attesters.iter().any(|id| id == &_sender), "Invalid duplicate signings");
+            attesters.push(_sender.clone());
+
+            let stake_sum = Self::sum_stakes(attesters.iter()
+                .map(|s| Self::approval_weight(<balances::Module<T>>::total_balance(s))));
+
+            if Self::is_approved(attesters.len(), stake_sum) {
+                if record.completed {
+                    let refund_to = Self::withdraw_refund_to(record_hash).unwrap_or_else(|| record.target.clone());
+                    <balances::Module<T>>::increase_free_balance_creating(&refund_to, record.quantity);
+                } else {
+                    Self::release_withdraw_reserved(&record.target, record.quantity);
+                    if let Some(start) = <WithdrawCountedInWindow<T>>::take(record_hash) {
+                        Self::release_withdraw_in_window(&record.target, start);
                     }
+                }
+                <WithdrawFailed<T>>::insert(record_hash, true);
+                Self::deposit_event(RawEvent::WithdrawFailed(record.target, record_hash, record.quantity));
+            }
+            <WithdrawFailureAttesters<T>>::insert(record_hash, attesters);
+
+            Self::note_relayer_submission(&_sender);
+            Ok(())
+        }
+
+        /// Submit the oracle's signature over a deposit, required alongside the authority
+        /// threshold while an oracle key is configured. Finalizes the deposit if the
+        /// authorities have already approved it.
+        pub fn confirm_deposit_oracle(origin, transaction_hash: T::Hash, log_index: LogIndex, signature: H512) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_deposits_active()?;
+            let key = (transaction_hash, log_index);
+
+            let oracle = match Self::oracle_key() {
+                Some(oracle) => oracle,
+                None => { return Err("No oracle configured") },
+            };
+            let mut record = match <DepositOf<T>>::get(key) {
+                Some(record) => record,
+                None => { return Err("Invalid transaction hash") },
+            };
+            ensure!(!record.completed, "Transaction already completed");
+            ensure!(!Self::oracle_confirmed(key), "Deposit already confirmed by oracle");
+            ensure!(Self::review_queue(key).is_none(), "Deposit under review");
+            ensure!(!Self::deposit_rejected(key), "Deposit rejected");
+
+            let message = Self::deposit_oracle_message(&record.target, transaction_hash, log_index, record.quantity);
+            ensure!(runtime_io::ed25519_verify(&signature.0, &message, oracle), "Invalid oracle signature");
+
+            if Self::deposit_approved(&key, &record) {
+                Self::approve_deposit(&key, &mut record)?;
+                <DepositOf<T>>::insert(key, record);
+            }
+            <OracleConfirmed<T>>::insert(key, true);
+
+            Self::note_relayer_submission(&_sender);
+            Ok(())
+        }
+
+        /// Approve a deposit with a single aggregate signature from an off-chain
+        /// threshold-signature committee, standing in for the authorities' signatures.
+        /// The deposit is registered if it is not already.
+        pub fn deposit_threshold_sig(origin, target: T::AccountId, transaction_hash: T::Hash, log_index: LogIndex, quantity: T::Balance, committee_sig: H512) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_deposits_active()?;
+            let key = (transaction_hash, log_index);
+
+            let group_key = match Self::committee_group_key() {
+                Some(group_key) => group_key,
+                None => { return Err("No committee group key configured") },
+            };
+            let existing = <DepositOf<T>>::get(key);
+            let mut record = match existing {
+                Some(ref record) => {
+                    ensure!(record.target == target, "Accounts do not match");
+                    ensure!(record.quantity == quantity, "Quantities don't match");
+                    ensure!(!record.completed, "Transaction already completed");
+                    ensure!(Self::review_queue(key).is_none(), "Deposit under review");
+                    ensure!(!Self::deposit_rejected(key), "Deposit rejected");
+                    record.clone()
                 },
-                None => { return Err("Invalid record hash") },
+                None => {
+                    // The committee may only approve deposits the user has registered
+                    ensure!(!Self::require_intent_before_attestation(), "Deposit intent required");
+                    DepositRecord {
+                        index: Self::deposit_count(),
+                        target: target.clone(),
+                        quantity: quantity,
+                        signers: vec![],
+                        completed: false,
+                    }
+                },
+            };
+            ensure!(Self::oracle_satisfied(&key), "Oracle confirmation required");
+
+            let message = Self::deposit_threshold_message(&target, transaction_hash, log_index, quantity);
+            ensure!(runtime_io::ed25519_verify(&committee_sig.0, &message, group_key), "Invalid committee signature");
+
+            // Nothing is written until every check has passed. A new deposit is registered
+            // before it is approved, so its events come in the usual order
+            if existing.is_none() {
+                let mut deposits = <Deposits<T>>::get();
+                deposits.push(key);
+                <Deposits<T>>::put(deposits);
+                <DepositCount<T>>::mutate(|i| *i += 1);
+                <DepositCreatedAt<T>>::insert(key, <system::Module<T>>::block_number());
+                Self::deposit_event(RawEvent::Deposit(target, transaction_hash, log_index, quantity));
+            }
+            Self::approve_deposit(&key, &mut record)?;
+            <DepositOf<T>>::insert(key, record);
+
+            Self::note_relayer_submission(&_sender);
+            Ok(())
+        }
+
+        /// Finalize a pending deposit from the trusted relayer in a single call,
+        /// skipping the authority signing threshold. Only usable while instant
+        /// finalization has been enabled by root, which is intended for testnets.
+        pub fn instant_finalize(origin, transaction_hash: T::Hash, log_index: LogIndex) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_deposits_active()?;
+            ensure!(Self::instant_finalize_enabled(), "Instant finalization disabled");
+            ensure!(Self::trusted_relayer() == Some(_sender.clone()), "Invalid non-trusted relayer");
+            let key = (transaction_hash, log_index);
+
+            match <DepositOf<T>>::get(key) {
+                Some(mut record) => {
+                    ensure!(!record.completed, "Transaction already completed");
+                    Self::credit_deposit(&record.target, record.quantity)?;
+                    record.completed = true;
+                    Self::note_deposit_receipt(&key, record.quantity);
+                    <ReviewQueue<T>>::remove(key);
+                    <DepositRejected<T>>::remove(key);
+                    Self::deposit_event(RawEvent::InstantFinalized(record.target.clone(), transaction_hash, log_index, record.quantity, Self::next_message_nonce()));
+                    <DepositOf<T>>::insert(key, record);
+                },
+                None => { return Err("Invalid transaction hash") },
+            }
+
+            Self::note_relayer_submission(&_sender);
+            Ok(())
+        }
+
+        /// Schedule the authority set to be replaced by `next_set` at the end of block
+        /// `activate_at`, superseding any rotation already scheduled. Once applied, the
+        /// set is kept across session changes until `release_authorities_to_session`.
+        ///
+        /// `next_set` holds accounts only, not `(AccountId, Balance)` pairs, since the
+        /// module reads each authority's stake from their balance and stores none of
+        /// its own. The swap happens in `on_finalise`, as this version of the runtime
+        /// has no `on_initialise` hook.
+        pub fn schedule_authority_set(origin, next_set: Vec<T::AccountId>, activate_at: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            ensure!(!next_set.is_empty(), "Invalid empty authority set");
+            ensure!(!next_set.iter().enumerate().any(|(i, a)| next_set[..i].contains(a)), "Duplicate authority");
+            ensure!(activate_at > <system::Module<T>>::block_number(), "Activation must be in the future");
+            <ScheduledAuthorities<T>>::put((activate_at, next_set));
+            Ok(())
+        }
+
+        /// Let session changes set the authorities from the session validators again,
+        /// after governance installed a set of its own.
+        pub fn release_authorities_to_session(origin) -> Result {
+            ensure_root(origin)?;
+            ensure!(Self::governance_authorities(), "Authorities already follow session");
+            <GovernanceAuthorities<T>>::kill();
+            Ok(())
+        }
+
+        /// Cancel a scheduled authority rotation before it activates.
+        pub fn cancel_scheduled_authority_set(origin) -> Result {
+            ensure_root(origin)?;
+            ensure!(Self::scheduled_authorities().is_some(), "No scheduled rotation");
+            <ScheduledAuthorities<T>>::kill();
+            Ok(())
+        }
+
+        /// Publish off-chain contact information for the sender's authority, such as an
+        /// operator name or endpoint. Informational only.
+        pub fn set_authority_metadata(origin, data: Vec<u8>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
+            ensure!(data.len() <= MAX_AUTHORITY_METADATA_LEN, "Metadata too long");
+            <AuthorityMeta<T>>::insert(_sender, data);
+            Ok(())
+        }
+
+        /// Propose replacing the sender's authority key with `new`. The migration can be
+        /// executed once the migration delay has passed, unless vetoed before then.
+        /// Nothing can be proposed until governance has set a non-zero delay, so there
+        /// is always a window for a veto.
+        pub fn propose_authority_migration(origin, new: T::AccountId) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let delay = Self::authority_migration_delay();
+            ensure!(!delay.is_zero(), "No migration delay configured");
+            ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
+            ensure!(!Self::authorities().iter().any(|id| id == &new), "New key is already an authority");
+            ensure!(Self::pending_authority_migration(&_sender).is_none(), "Migration already pending");
+
+            let executable_at = <system::Module<T>>::block_number() + delay;
+            <PendingAuthorityMigration<T>>::insert(_sender, (new, executable_at));
+            Ok(())
+        }
+
+        /// Replace `old` with `new` in the authority set once their proposed migration's
+        /// delay has passed. Later session changes keep substituting `new` for `old`
+        /// among the session validators.
+        pub fn execute_authority_migration(origin, old: T::AccountId, new: T::AccountId) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let executable_at = match Self::pending_authority_migration(&old) {
+                Some((ref pending, executable_at)) if *pending == new => executable_at,
+                _ => { return Err("No pending migration") },
+            };
+            ensure!(<system::Module<T>>::block_number() >= executable_at, "Migration still timelocked");
+            ensure!(Self::authorities().iter().any(|id| id == &old), "Invalid non-authority sender");
+            ensure!(!Self::authorities().iter().any(|id| id == &new), "New key is already an authority");
+
+            <PendingAuthorityMigration<T>>::remove(&old);
+            // `new` may itself have migrated away before. Dropping that substitution
+            // keeps the substitutions free of cycles, so following them terminates
+            <MigratedAuthorityKey<T>>::remove(&new);
+            <MigratedAuthorityKey<T>>::insert(&old, new.clone());
+            let next_set = Self::authorities().into_iter()
+                .map(|id| if id == old { new.clone() } else { id })
+                .collect();
+            Self::set_authorities(next_set);
+            Ok(())
+        }
+
+        /// Cancel a pending authority key migration during its timelock.
+        pub fn veto_authority_migration(origin, old: T::AccountId) -> Result {
+            ensure_root(origin)?;
+            ensure!(Self::pending_authority_migration(&old).is_some(), "No pending migration");
+            <PendingAuthorityMigration<T>>::remove(&old);
+            Ok(())
+        }
+
+        /// Set the number of blocks a proposed authority key migration must wait.
+        pub fn set_authority_migration_delay(origin, delay: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            ensure!(!delay.is_zero(), "Invalid zero migration delay");
+            <AuthorityMigrationDelay<T>>::put(delay);
+            Ok(())
+        }
+
+        /// Expire deposit intents that no authority has attested to within `blocks` of
+        /// their registration. A value of zero disables expiry for new intents.
+        pub fn set_deposit_intent_expiry(origin, blocks: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            <DepositIntentExpiry<T>>::put(blocks);
+            Ok(())
+        }
+
+        /// Decay the stake each authority contributes to a deposit by `per_block` per mille
+        /// for every block they wait after its creation before signing, up to `max` per
+        /// mille. A `per_block` of zero disables decay.
+        pub fn set_approval_decay(origin, per_block: u32, max: u32) -> Result {
+            ensure_root(origin)?;
+            ensure!(max <= 1000, "Invalid decay above 100%");
+            <ApprovalDecayPerBlock<T>>::put(per_block);
+            <ApprovalDecayMax<T>>::put(max);
+            Ok(())
+        }
+
+        /// Require deposits to be registered by a non-authority account, normally the
+        /// depositor, before authorities can attest to them.
+        pub fn set_require_intent_before_attestation(origin, required: bool) -> Result {
+            ensure_root(origin)?;
+            <RequireIntentBeforeAttestation<T>>::put(required);
+            Ok(())
+        }
+
+        /// Accept an authority signing a deposit they have already signed as a no-op
+        /// instead of rejecting it, so relayer retries are safe.
+        pub fn set_idempotent_attestations(origin, enabled: bool) -> Result {
+            ensure_root(origin)?;
+            <IdempotentAttestations<T>>::put(enabled);
+            Ok(())
+        }
+
+        /// Measure authority participation over windows of `length` blocks, or over the
+        /// whole chain with a zero length.
+        pub fn set_participation_window(origin, length: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            <ParticipationWindowLength<T>>::put(length);
+            Ok(())
+        }
+
+        /// Halt or resume both directions of the bridge.
+        pub fn set_paused(origin, paused: bool) -> Result {
+            ensure_root(origin)?;
+            <Paused<T>>::put(paused);
+            Ok(())
+        }
+
+        /// Halt or resume inbound deposits only.
+        pub fn set_deposits_paused(origin, paused: bool) -> Result {
+            ensure_root(origin)?;
+            <DepositsPaused<T>>::put(paused);
+            Ok(())
+        }
+
+        /// Halt or resume outbound withdraws only.
+        pub fn set_withdraws_paused(origin, paused: bool) -> Result {
+            ensure_root(origin)?;
+            <WithdrawsPaused<T>>::put(paused);
+            Ok(())
+        }
+
+        /// Credit deposits that were escrowed for the sender while their account did not
+        /// exist, once the account has been funded.
+        pub fn claim_escrowed_deposit(origin) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(!Self::escrowed_deposit_of(&_sender).is_zero(), "No escrowed deposit");
+            ensure!(!<balances::Module<T>>::total_balance(&_sender).is_zero(), "Below existential deposit");
+            Self::credit_deposit(&_sender, Zero::zero())
+        }
+
+        /// Choose whether deposits below the existential deposit for accounts that do not
+        /// exist yet are escrowed until the account is funded, or rejected.
+        pub fn set_escrow_below_existential(origin, enabled: bool) -> Result {
+            ensure_root(origin)?;
+            <EscrowBelowExistential<T>>::put(enabled);
+            Ok(())
+        }
+
+        /// Require an oracle signature, verified against `key`, for every deposit to
+        /// finalize, or stop requiring one with `None`.
+        pub fn set_oracle_key(origin, key: Option<H256>) -> Result {
+            ensure_root(origin)?;
+            match key {
+                Some(key) => <OracleKey<T>>::put(key),
+                None => <OracleKey<T>>::kill(),
+            }
+
+            Ok(())
+        }
+
+        /// Require a fixed number of authority signatures to approve requests instead of
+        /// a stake-weighted supermajority, or restore stake weighting with `None`.
+        pub fn set_count_threshold(origin, threshold: Option<u32>) -> Result {
+            ensure_root(origin)?;
+            match threshold {
+                Some(0) => { return Err("Invalid zero count threshold") },
+                Some(k) => <CountThreshold<T>>::put(k),
+                None => <CountThreshold<T>>::kill(),
+            }
+
+            Ok(())
+        }
+
+        /// Credit `target` with `amount` outside the deposit flow, for recovering deposits
+        /// that were verified but cannot be processed. Every use is logged with `reason`.
+        pub fn force_credit(origin, target: T::AccountId, amount: T::Balance, reason: Vec<u8>) -> Result {
+            ensure_root(origin)?;
+            ensure!(!reason.is_empty(), "Invalid empty reason");
+            <balances::Module<T>>::increase_free_balance_creating(&target, amount);
+            <ManualInterventions<T>>::mutate(|log| log.push(ManualIntervention {
+                target: target.clone(),
+                quantity: amount,
+                reason: reason,
+                block: <system::Module<T>>::block_number(),
+            }));
+            Self::deposit_event(RawEvent::ManualCredit(target, amount));
+            Ok(())
+        }
+
+        /// Weight signers by the square root of their stake instead of linearly, and
+        /// measure approval against the authorities' combined weight.
+        pub fn set_quadratic_approval(origin, enabled: bool) -> Result {
+            ensure_root(origin)?;
+            <QuadraticApproval<T>>::put(enabled);
+            Ok(())
+        }
+
+        /// Credit a deposit that was rejected on approval because it could not be
+        /// credited, once that has been resolved, for instance by funding the target or
+        /// enabling escrow.
+        pub fn release_rejected_deposit(origin, transaction_hash: T::Hash, log_index: LogIndex) -> Result {
+            ensure_root(origin)?;
+            let key = (transaction_hash, log_index);
+            ensure!(Self::deposit_rejected(key), "Deposit not rejected");
+            let mut record = match <DepositOf<T>>::get(key) {
+                Some(record) => record,
+                None => { return Err("Invalid transaction hash") },
+            };
+            ensure!(!record.completed, "Transaction already completed");
+
+            Self::finalize_deposit(&key, &mut record)?;
+            <DepositRejected<T>>::remove(key);
+            <DepositOf<T>>::insert(key, record);
+            Ok(())
+        }
+
+        /// Release a deposit held for review, crediting it before its review delay ends.
+        pub fn release_reviewed(origin, transaction_hash: T::Hash, log_index: LogIndex) -> Result {
+            ensure_root(origin)?;
+            let key = (transaction_hash, log_index);
+            ensure!(Self::review_queue(key).is_some(), "Deposit not under review");
+            let mut record = match <DepositOf<T>>::get(key) {
+                Some(record) => record,
+                None => { return Err("Invalid transaction hash") },
+            };
+            ensure!(!record.completed, "Transaction already completed");
+
+            Self::finalize_deposit(&key, &mut record)?;
+            <ReviewQueue<T>>::remove(key);
+            <DepositOf<T>>::insert(key, record);
+            Ok(())
+        }
+
+        /// Hold approved deposits of at least `threshold` for review, releasing them
+        /// automatically `delay` blocks after approval unless governance releases them
+        /// sooner. A `threshold` of zero disables review.
+        pub fn set_review(origin, threshold: T::Balance, delay: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            <ReviewThreshold<T>>::put(threshold);
+            <ReviewDelay<T>>::put(delay);
+            Ok(())
+        }
+
+        /// Accept aggregate deposit signatures verified against the committee's ed25519
+        /// group `key`, or stop accepting them with `None`.
+        pub fn set_committee_group_key(origin, key: Option<H256>) -> Result {
+            ensure_root(origin)?;
+            match key {
+                Some(key) => <CommitteeGroupKey<T>>::put(key),
+                None => <CommitteeGroupKey<T>>::kill(),
+            }
+
+            Ok(())
+        }
+
+        /// Sample a committee of `size` authorities for each deposit, seeded by `seed`, and
+        /// only count their signatures towards it. A `size` of zero, or one covering the
+        /// whole authority set, counts every authority.
+        pub fn set_committee(origin, size: u32, seed: T::Hash) -> Result {
+            ensure_root(origin)?;
+            <CommitteeSize<T>>::put(size);
+            <CommitteeSeed<T>>::put(seed);
+            Ok(())
+        }
+
+        /// Limit how many blocks ahead a withdraw may set its expiry. Later expiries are
+        /// brought forward to the limit. A `max` of zero leaves expiries unbounded.
+        pub fn set_max_withdraw_expiry(origin, max: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            <MaxWithdrawExpiry<T>>::put(max);
+            Ok(())
+        }
+
+        /// Bound the quantity of a single withdraw. A `max` of zero leaves withdraws
+        /// unbounded above.
+        pub fn set_withdraw_limits(origin, min: T::Balance, max: T::Balance) -> Result {
+            ensure_root(origin)?;
+            ensure!(max.is_zero() || min <= max, "Invalid withdraw limits");
+            <MinWithdraw<T>>::put(min);
+            <MaxWithdraw<T>>::put(max);
+            Ok(())
+        }
+
+        /// Limit how many withdraws a single account may make within each window of
+        /// `length` blocks, or remove the limit with `None`.
+        pub fn set_withdraw_window_limit(origin, length: T::BlockNumber, max: Option<u32>) -> Result {
+            ensure_root(origin)?;
+            ensure!(max.is_none() || !length.is_zero(), "Invalid zero window length");
+            <WithdrawWindowLength<T>>::put(length);
+            match max {
+                Some(max) => <MaxWithdrawsPerAccountPerWindow<T>>::put(max),
+                None => <MaxWithdrawsPerAccountPerWindow<T>>::kill(),
+            }
+
+            Ok(())
+        }
+
+        /// Enable or disable instant finalization by the given trusted relayer.
+        /// This must never be enabled on a production network.
+        pub fn set_instant_finalize(origin, enabled: bool, relayer: T::AccountId) -> Result {
+            ensure_root(origin)?;
+            <InstantFinalizeEnabled<T>>::put(enabled);
+            if enabled {
+                <TrustedRelayer<T>>::put(relayer);
+            } else {
+                <TrustedRelayer<T>>::kill();
             }
 
             Ok(())
@@ -222,22 +875,731 @@ decl_module! {
 }
 
 impl<T: Trait> Module<T> {
+    /// The version of the record and message layouts in use by this module.
+    pub fn bridge_version() -> u32 {
+        BRIDGE_VERSION
+    }
+
     pub fn withdraw_record_hash(index: usize) -> T::Hash {
         return <Withdraws<T>>::get()[index];
     }
+
+    /// The key `who` signs with as an authority, following any executed key migrations.
+    fn migrated_authority(who: T::AccountId) -> T::AccountId {
+        let mut who = who;
+        while let Some(next) = Self::migrated_authority_key(&who) {
+            who = next;
+        }
+        who
+    }
+
+    /// Replace the authority set, advancing the set id and committing to the new
+    /// members with a trie root so light clients can follow the change.
+    fn set_authorities(authorities: Vec<T::AccountId>) {
+        let set_id = Self::authority_set_id() + 1;
+        let root = Self::authorities_root(&authorities);
+        let member_count = authorities.len() as u32;
+
+        <Authorities<T>>::put(authorities.clone());
+        <AuthoritySetId<T>>::put(set_id);
+        <AuthoritySetRoot<T>>::put(root);
+        Self::deposit_event(RawEvent::NewAuthorities(authorities));
+        Self::deposit_event(RawEvent::AuthoritySetChanged(set_id, root, member_count));
+    }
+
+    /// The ordered trie root of the encoded members of an authority set.
+    pub fn authorities_root(authorities: &[T::AccountId]) -> T::Hash {
+        let encoded = authorities.iter().map(|a| a.encode()).collect::<Vec<_>>();
+        T::Hashing::enumerated_trie_root(&encoded.iter().map(|e| &e[..]).collect::<Vec<_>>())
+    }
+
+    /// Record that an extrinsic relaying bridge data was submitted by `who`.
+    fn note_relayer_submission(who: &T::AccountId) {
+        <RelayerSubmitCount<T>>::mutate(who, |count| *count += 1);
+    }
+
+    /// The stake `who` contributes towards the deposit at `key`. Under approval decay
+    /// this is reduced by how many blocks after the deposit's creation they signed.
+    pub fn effective_stake(key: &(T::Hash, LogIndex), who: &T::AccountId) -> T::Balance {
+        Self::stake_signed_at(key, who, Self::deposit_signed_at((key.clone(), who.clone())))
+    }
+
+    /// The stake `who` would contribute towards the deposit at `key` by signing at
+    /// block `signed_at`.
+    fn stake_signed_at(key: &(T::Hash, LogIndex), who: &T::AccountId, signed_at: T::BlockNumber) -> T::Balance {
+        let stake = Self::approval_weight(<balances::Module<T>>::total_balance(who));
+        let per_block = Self::approval_decay_per_block() as u64;
+        if per_block == 0 {
+            return stake;
+        }
+
+        let created_at = Self::deposit_created_at(key);
+        let elapsed = if signed_at > created_at { (signed_at - created_at).as_() } else { 0 };
+        let decay = rstd::cmp::min(per_block.saturating_mul(elapsed), Self::approval_decay_max() as u64);
+
+        // Split the multiplication so large stakes cannot overflow
+        let kept = <T::Balance as As<u64>>::sa(1000 - decay);
+        let per_mille = <T::Balance as As<u64>>::sa(1000);
+        stake / per_mille * kept + stake % per_mille * kept / per_mille
+    }
+
+    /// Whether `sender` signing the deposit at `key` repeats their earlier signature
+    /// while idempotent attestations are enabled. Only an exact retry qualifies, so
+    /// mismatched targets or quantities still fail.
+    fn is_idempotent_retry(sender: &T::AccountId, target: &T::AccountId, key: &(T::Hash, LogIndex), quantity: T::Balance) -> bool {
+        if !Self::idempotent_attestations() {
+            return false;
+        }
+        Self::deposit_of(key).map_or(false, |record| {
+            record.target == *target && record.quantity == quantity
+                && record.signers.iter().any(|id| id == sender)
+        })
+    }
+
+    /// Validate `sender` signing the deposit at `key` in the current block, returning
+    /// the record with the new signer added and whether it can now be finalized.
+    fn prepare_sign_deposit(
+        sender: &T::AccountId,
+        target: &T::AccountId,
+        key: &(T::Hash, LogIndex),
+        quantity: T::Balance
+    ) -> rstd::result::Result<(DepositRecord<T::AccountId, T::Balance>, bool), &'static str> {
+        let mut record = match Self::deposit_of(key) {
+            Some(record) => record,
+            None => { return Err("Invalid transaction hash") },
+        };
+
+        // Ensure all parameters match for safety
+        ensure!(record.target == *target, "Accounts do not match");
+        ensure!(record.quantity == quantity, "Quantities don't match");
+        ensure!(!record.completed, "Transaction already completed");
+        ensure!(Self::review_queue(key).is_none(), "Deposit under review");
+        ensure!(!Self::deposit_rejected(key), "Deposit rejected");
+        // Ensure sender is a bridge authority
+        ensure!(Self::authorities().iter().any(|id| id == sender), "Invalid non-authority sender");
+        ensure!(Self::in_deposit_committee(key, sender), "Invalid non-committee sender");
+        // Ensure senders can't sign twice
+        ensure!(!record.signers.iter().any(|id| id == sender), "Invalid duplicate signings");
+        // Add record update with new signer
+        record.signers.push(sender.clone());
+
+        // Check if we have reached enough signers for the deposit
+        // TODO: Ensure that checking balances is sufficient vs. finding explicit stake amounts
+        let now = <system::Module<T>>::block_number();
+        let approved = Self::deposit_threshold_met(key, &record.signers, |s| {
+            if s == sender { Self::stake_signed_at(key, s, now) } else { Self::effective_stake(key, s) }
+        }) && Self::oracle_satisfied(key);
+        Ok((record, approved))
+    }
+
+    /// Whether the signers already on a deposit record meet the approval threshold.
+    fn deposit_approved(key: &(T::Hash, LogIndex), record: &DepositRecord<T::AccountId, T::Balance>) -> bool {
+        Self::deposit_threshold_met(key, &record.signers, |s| Self::effective_stake(key, s))
+    }
+
+    /// Whether `signers` approve the deposit at `key`, with `stake_of` giving each
+    /// signer's contribution. While the deposit has a committee only its members
+    /// count, and their stake is measured against the committee's total stake rather
+    /// than the total issuance.
+    fn deposit_threshold_met<F>(key: &(T::Hash, LogIndex), signers: &[T::AccountId], stake_of: F) -> bool
+        where F: Fn(&T::AccountId) -> T::Balance
+    {
+        let committee = match Self::deposit_committee(key) {
+            Some(committee) => committee,
+            None => {
+                let stake_sum = Self::sum_stakes(signers.iter().map(|s| stake_of(s)));
+                return Self::is_approved(signers.len(), stake_sum);
+            },
+        };
+
+        let members = signers.iter().filter(|s| committee.contains(s)).collect::<Vec<_>>();
+        if let Some(threshold) = Self::count_threshold() {
+            return members.len() >= threshold as usize;
+        }
+
+        let total = Self::sum_stakes(committee.iter()
+            .map(|c| Self::approval_weight(<balances::Module<T>>::total_balance(c))));
+        if total.is_zero() {
+            return false;
+        }
+
+        let stake_sum = Self::sum_stakes(members.iter().map(|s| stake_of(s)));
+        VoteThreshold::SuperMajorityApprove.approved(stake_sum, total - stake_sum, total, total)
+    }
+
+    /// The authorities sampled to sign the deposit at `key`, or `None` when every
+    /// authority counts. Sampling is deterministic in the committee seed and the key.
+    pub fn deposit_committee(key: &(T::Hash, LogIndex)) -> Option<Vec<T::AccountId>> {
+        let size = Self::committee_size() as usize;
+        let mut pool = Self::authorities();
+        if size == 0 || size >= pool.len() {
+            return None;
+        }
+
+        let seed = Self::committee_seed();
+        let mut committee = Vec::with_capacity(size);
+        let mut round = 0u32;
+        while committee.len() < size {
+            let draw = T::Hashing::hash_of(&(seed, *key, round));
+            let draw = draw.as_ref().iter().take(8).fold(0u64, |a, b| (a << 8) | *b as u64);
+            committee.push(pool.swap_remove((draw % pool.len() as u64) as usize));
+            round += 1;
+        }
+        Some(committee)
+    }
+
+    /// Whether `who` may sign the deposit at `key` under its committee, if any.
+    fn in_deposit_committee(key: &(T::Hash, LogIndex), who: &T::AccountId) -> bool {
+        Self::deposit_committee(key).map_or(true, |committee| committee.contains(who))
+    }
+
+    /// Whether the deposit at `key` has the oracle confirmation it needs, if any.
+    fn oracle_satisfied(key: &(T::Hash, LogIndex)) -> bool {
+        Self::oracle_key().is_none() || Self::oracle_confirmed(key)
+    }
+
+    /// The message the oracle signs to confirm a deposit, domain separated by the
+    /// bridge version.
+    pub fn deposit_oracle_message(target: &T::AccountId, transaction_hash: T::Hash, log_index: LogIndex, quantity: T::Balance) -> Vec<u8> {
+        (BRIDGE_VERSION, b"oracle".to_vec(), transaction_hash, log_index, target, quantity).encode()
+    }
+
+    /// Finalize an approved deposit, hold it for review if it is flagged, or reject it
+    /// if it cannot be credited.
+    fn approve_deposit(key: &(T::Hash, LogIndex), record: &mut DepositRecord<T::AccountId, T::Balance>) -> Result {
+        // A deposit that cannot be credited is set aside for governance instead of
+        // failing the approving call, which would leave it stuck for every signer
+        if Self::creditable_amount(&record.target, record.quantity).is_err() {
+            <DepositRejected<T>>::insert(*key, true);
+            Self::deposit_event(RawEvent::DepositRejected(key.0, key.1));
+            return Ok(());
+        }
+        if !Self::flagged_for_review(record) {
+            return Self::finalize_deposit(key, record);
+        }
+
+        let release_at = <system::Module<T>>::block_number() + Self::review_delay();
+        <ReviewQueue<T>>::insert(*key, release_at);
+        <ReviewReleasesAt<T>>::mutate(release_at, |keys| keys.push(*key));
+        Self::deposit_event(RawEvent::DepositHeldForReview(key.0, key.1, release_at));
+        Ok(())
+    }
+
+    /// Whether an approved deposit must wait in the review queue before crediting.
+    fn flagged_for_review(record: &DepositRecord<T::AccountId, T::Balance>) -> bool {
+        let threshold = Self::review_threshold();
+        !threshold.is_zero() && record.quantity >= threshold
+    }
+
+    /// Credit the deposits whose review delay ends at block `n`. Any that cannot be
+    /// credited stay queued for governance to release. While deposits are paused the
+    /// releases are put off to the next block.
+    fn release_reviewed_deposits(n: T::BlockNumber) {
+        let keys = <ReviewReleasesAt<T>>::take(n);
+        if Self::ensure_deposits_active().is_err() {
+            if !keys.is_empty() {
+                <ReviewReleasesAt<T>>::mutate(n + One::one(), |next| next.extend(keys));
+            }
+            return;
+        }
+
+        for key in keys {
+            if Self::review_queue(key).is_none() {
+                continue;
+            }
+            if let Some(mut record) = <DepositOf<T>>::get(key) {
+                if record.completed {
+                    <ReviewQueue<T>>::remove(key);
+                    continue;
+                }
+                if Self::finalize_deposit(&key, &mut record).is_ok() {
+                    <ReviewQueue<T>>::remove(key);
+                    <DepositOf<T>>::insert(key, record);
+                }
+            }
+        }
+    }
+
+    /// The message an off-chain committee signs with its group key to approve a
+    /// deposit through `deposit_threshold_sig`, domain separated by the bridge version.
+    pub fn deposit_threshold_message(target: &T::AccountId, transaction_hash: T::Hash, log_index: LogIndex, quantity: T::Balance) -> Vec<u8> {
+        (BRIDGE_VERSION, b"committee".to_vec(), transaction_hash, log_index, target, quantity).encode()
+    }
+
+    /// Credit an approved deposit and mark it completed.
+    fn finalize_deposit(key: &(T::Hash, LogIndex), record: &mut DepositRecord<T::AccountId, T::Balance>) -> Result {
+        Self::credit_deposit(&record.target, record.quantity)?;
+        record.completed = true;
+        // Only finalizations reached by authority signatures count towards participation
+        if !record.signers.is_empty() {
+            Self::note_participation(&record.signers);
+        }
+        Self::note_deposit_receipt(key, record.quantity);
+        Self::deposit_event(RawEvent::DepositFinalized(record.target.clone(), key.0, key.1, record.quantity, Self::next_message_nonce()));
+        Ok(())
+    }
+
+    /// Take the next global cross-chain message nonce.
+    fn next_message_nonce() -> u64 {
+        let nonce = Self::message_nonce();
+        <MessageNonce<T>>::put(nonce + 1);
+        nonce
+    }
+
+    /// Store the receipt for a deposit finalized in the current block.
+    fn note_deposit_receipt(key: &(T::Hash, LogIndex), quantity: T::Balance) {
+        let now = <system::Module<T>>::block_number();
+        // Deposits registered before creation blocks were recorded, or registered and
+        // finalized in the same call, have no latency to measure
+        let latency = if <DepositCreatedAt<T>>::exists(key) {
+            let created_at = Self::deposit_created_at(key);
+            Some(if now > created_at { now - created_at } else { Zero::zero() })
+        } else {
+            None
+        };
+
+        if let Some(latency) = latency {
+            let start = Self::window_start(Self::participation_window_length());
+            let (last_start, total, count) = Self::latency_in_window();
+            let (total, count) = if last_start == start { (total, count) } else { (0, 0) };
+            <LatencyInWindow<T>>::put((start, total.saturating_add(latency.as_()), count.saturating_add(1)));
+        }
+
+        <DepositReceiptOf<T>>::insert(*key, DepositReceipt {
+            transaction_hash: key.0,
+            log_index: key.1,
+            quantity,
+            finalized_at: now,
+            latency,
+        });
+    }
+
+    /// The average number of blocks between creation and finalization, rounded down,
+    /// of the deposits finalized in the current participation window whose creation
+    /// block is known.
+    pub fn average_finalization_latency() -> Option<u64> {
+        let start = Self::window_start(Self::participation_window_length());
+        let (last_start, total, count) = Self::latency_in_window();
+        if last_start != start || count == 0 {
+            return None;
+        }
+        Some(total / count as u64)
+    }
+
+    /// Project what submitting the deposit at `(transaction_hash, log_index)` from
+    /// `sender` would do without changing any state: create the record if it does not
+    /// exist yet, otherwise sign it.
+    pub fn simulate_deposit(
+        target: T::AccountId,
+        transaction_hash: T::Hash,
+        log_index: LogIndex,
+        quantity: T::Balance,
+        sender: T::AccountId
+    ) -> DepositSimOutcome {
+        if let Err(err) = Self::ensure_deposits_active() {
+            return DepositSimOutcome::Error(err);
+        }
+
+        let key = (transaction_hash, log_index);
+        if Self::deposit_of(&key).is_none() {
+            if Self::require_intent_before_attestation() && Self::authorities().iter().any(|a| a == &sender) {
+                return DepositSimOutcome::Error("Deposit intent required");
+            }
+            return DepositSimOutcome::Create;
+        }
+        if Self::is_idempotent_retry(&sender, &target, &key, quantity) {
+            return DepositSimOutcome::Unchanged;
+        }
+
+        match Self::prepare_sign_deposit(&sender, &target, &key, quantity) {
+            Ok((record, true)) => {
+                if Self::creditable_amount(&record.target, record.quantity).is_err() {
+                    DepositSimOutcome::Reject
+                } else if Self::flagged_for_review(&record) {
+                    DepositSimOutcome::Append
+                } else {
+                    DepositSimOutcome::Finalize
+                }
+            },
+            Ok((_, false)) => DepositSimOutcome::Append,
+            Err(err) => DepositSimOutcome::Error(err),
+        }
+    }
+
+    /// Unfinalized deposits created more than `older_than` blocks ago, which likely
+    /// need attention from authorities or governance.
+    pub fn stuck_deposits(older_than: T::BlockNumber) -> Vec<(T::Hash, LogIndex)> {
+        let now = <system::Module<T>>::block_number();
+        if older_than > now {
+            return vec![];
+        }
+
+        let cutoff = now - older_than;
+        Self::deposits().into_iter()
+            .filter(|key| Self::deposit_created_at(key) < cutoff)
+            .filter(|key| Self::deposit_of(key).map_or(false, |record| !record.completed))
+            .collect()
+    }
+
+    /// Move deposits recorded before they were keyed by log index to the key
+    /// `(transaction_hash, 0)`. This runs once, at the end of the first block after
+    /// the upgrade, and is a no-op afterwards and on chains started with log index
+    /// keys. Deposit calls in that block would run before it, so deposits should be
+    /// paused across the upgrade.
+    pub fn migrate_deposit_keys() {
+        if Self::deposit_keys_migrated() {
+            return;
+        }
+
+        let deposits_key = <Deposits<T> as generator::StorageValue<Vec<(T::Hash, LogIndex)>>>::key();
+        let prefix = <DepositOf<T> as generator::StorageMap<(T::Hash, LogIndex), DepositRecord<T::AccountId, T::Balance>>>::prefix();
+        let legacy_deposits = storage::get::<Vec<T::Hash>>(deposits_key).unwrap_or_default();
+
+        let mut deposits = Vec::with_capacity(legacy_deposits.len());
+        for hash in legacy_deposits {
+            let mut legacy_key = prefix.to_vec();
+            hash.encode_to(&mut legacy_key);
+            if let Some(record) = storage::take::<DepositRecord<T::AccountId, T::Balance>>(&legacy_key) {
+                <DepositOf<T>>::insert((hash, 0), record);
+            }
+            deposits.push((hash, 0));
+        }
+
+        <Deposits<T>>::put(deposits);
+        <DepositKeysMigrated<T>>::put(true);
+    }
+
+    /// Ensure neither the bridge as a whole nor the deposit direction is paused.
+    fn ensure_deposits_active() -> Result {
+        ensure!(!Self::paused(), "Bridge paused");
+        ensure!(!Self::deposits_paused(), "Deposits paused");
+        Ok(())
+    }
+
+    /// Ensure neither the bridge as a whole nor the withdraw direction is paused.
+    fn ensure_withdraws_active() -> Result {
+        ensure!(!Self::paused(), "Bridge paused");
+        ensure!(!Self::withdraws_paused(), "Withdraws paused");
+        Ok(())
+    }
+
+    /// Add `sender`'s signature to the withdraw at `record_hash`, debiting the account
+    /// once the withdraw is approved. Nothing is written unless every check passes.
+    fn apply_withdraw_signature(sender: &T::AccountId, target: T::AccountId, record_hash: T::Hash, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>) -> Result {
+        match <WithdrawOf<T>>::get(record_hash) {
+            Some(mut record) => {
+                // Ensure all parameters match for safety
+                ensure!(record.target == target, "Accounts do not match");
+                ensure!(record.quantity == quantity, "Quantities don't match");
+                ensure!(!record.completed, "Transaction already completed");
+                ensure!(!Self::withdraw_failed(record_hash), "Withdraw failed");
+                ensure!(!Self::withdraw_expired(record_hash), "Withdraw expired");
+                // Ensure sender is a bridge authority if record exists
+                ensure!(Self::authorities().iter().any(|id| id == sender), "Invalid non-authority sender");
+                // Ensure senders can't sign twice
+                ensure!(!record.signers.iter().any(|s| s.0 == *sender), "Invalid duplicate signings");
+                // Add record update with new signer
+                record.signers.push((sender.clone(), signed_cross_chain_tx));
+
+                // Check if we have reached enough signers for the withdrawal
+                // TODO: Ensure that checking balances is sufficient vs. finding explicit stake amounts
+                let stake_sum = Self::sum_stakes(record.signers.iter()
+                    .map(|s| Self::approval_weight(<balances::Module<T>>::total_balance(&s.0))));
+
+                // Check if we approve the proposal
+                if Self::is_approved(record.signers.len(), stake_sum) {
+                    match <balances::Module<T>>::decrease_free_balance(&record.target, record.quantity) {
+                        Ok(_) => {
+                            // TODO: do we still mark completed on error? or store a "failed" tx?
+                            record.completed = true;
+                            Self::release_withdraw_reserved(&record.target, record.quantity);
+                            <WithdrawCountedInWindow<T>>::remove(record_hash);
+                            // TODO: fire event
+                        },
+                        Err(err) => { return Err(err); } // TODO test this?
+                    };
+                }
+                <WithdrawOf<T>>::insert(record_hash, record);
+            },
+            None => { return Err("Invalid record hash") },
+        }
+
+        Ok(())
+    }
+
+    /// Validate a requested withdraw expiry, bringing it forward to the governance limit.
+    fn clamp_withdraw_expiry(expire_at: Option<T::BlockNumber>) -> rstd::result::Result<Option<T::BlockNumber>, &'static str> {
+        let expire_at = match expire_at {
+            Some(expire_at) => expire_at,
+            None => { return Ok(None) },
+        };
+        let now = <system::Module<T>>::block_number();
+        ensure!(expire_at > now, "Expiry must be in the future");
+
+        let max = Self::max_withdraw_expiry();
+        if !max.is_zero() && expire_at > now + max {
+            return Ok(Some(now + max));
+        }
+        Ok(Some(expire_at))
+    }
+
+    /// Cancel the withdraws expiring at block `n` that are still awaiting approval.
+    /// Their balance was never burned, so there is nothing to refund, and the slot
+    /// each took in its sender's withdraw window is given back.
+    fn expire_withdraws(n: T::BlockNumber) {
+        for hash in <WithdrawsExpiringAt<T>>::take(n) {
+            let record = match <WithdrawOf<T>>::get(hash) {
+                Some(record) => record,
+                None => continue,
+            };
+            if record.completed || Self::withdraw_failed(hash) {
+                continue;
+            }
+
+            <WithdrawExpired<T>>::insert(hash, true);
+            Self::release_withdraw_reserved(&record.target, record.quantity);
+            if let Some(start) = <WithdrawCountedInWindow<T>>::take(hash) {
+                Self::release_withdraw_in_window(&record.target, start);
+            }
+            Self::deposit_event(RawEvent::WithdrawExpired(record.target, hash, record.quantity));
+        }
+    }
+
+    /// Stop counting `quantity` of `who`'s withdraws as pending. Withdraws pending from
+    /// before the figure was tracked may release more than was counted, so this floors
+    /// at zero.
+    fn release_withdraw_reserved(who: &T::AccountId, quantity: T::Balance) {
+        <WithdrawReserved<T>>::mutate(who, |reserved| {
+            *reserved = if *reserved > quantity { *reserved - quantity } else { Zero::zero() };
+        });
+    }
+
+    /// Give back a withdraw `who` made in the window starting at `start`, if that is
+    /// still the window their withdraws are being counted in.
+    fn release_withdraw_in_window(who: &T::AccountId, start: T::BlockNumber) {
+        let (last_start, count) = Self::withdraws_in_window(who);
+        if last_start == start && count > 0 {
+            <WithdrawsInWindow<T>>::insert(who, (last_start, count - 1));
+        }
+    }
+
+    /// Remove the deposit intents due to expire at block `n` that are still unattested.
+    fn expire_deposit_intents(n: T::BlockNumber) {
+        let expiring = <DepositIntentsExpiringAt<T>>::take(n);
+        if expiring.is_empty() {
+            return;
+        }
+
+        let mut deposits = <Deposits<T>>::get();
+        for key in expiring {
+            // Deposits approved by the committee or confirmed by the oracle are no longer
+            // bare intents, even without authority signers
+            let unattested = match <DepositOf<T>>::get(key) {
+                Some(record) => record.signers.is_empty() && !record.completed
+                    && Self::review_queue(key).is_none() && !Self::oracle_confirmed(key)
+                    && !Self::deposit_rejected(key),
+                None => false,
+            };
+            if unattested {
+                <DepositOf<T>>::remove(key);
+                <DepositCreatedAt<T>>::remove(key);
+                <OracleConfirmed<T>>::remove(key);
+                deposits.retain(|k| *k != key);
+                Self::deposit_event(RawEvent::DepositIntentExpired(key.0, key.1));
+            }
+        }
+        <Deposits<T>>::put(deposits);
+    }
+
+    /// Credit an approved deposit, along with anything already escrowed, to `target`.
+    /// If the target does not exist and the amount is below the existential deposit it
+    /// is either escrowed or rejected, so no dust account is created.
+    fn credit_deposit(target: &T::AccountId, quantity: T::Balance) -> Result {
+        let amount = Self::creditable_amount(target, quantity)?;
+        if Self::below_existential(target, amount) {
+            <EscrowedDepositOf<T>>::insert(target, amount);
+            return Ok(());
+        }
+
+        <EscrowedDepositOf<T>>::remove(target);
+        <balances::Module<T>>::increase_free_balance_creating(target, amount);
+        Ok(())
+    }
+
+    /// The amount crediting `quantity` to `target` would pay out, including anything
+    /// already escrowed, or why it cannot be credited.
+    fn creditable_amount(target: &T::AccountId, quantity: T::Balance) -> rstd::result::Result<T::Balance, &'static str> {
+        let amount = match Self::escrowed_deposit_of(target).checked_add(&quantity) {
+            Some(amount) => amount,
+            None => { return Err("Escrowed deposit overflow") },
+        };
+        if Self::below_existential(target, amount) {
+            ensure!(Self::escrow_below_existential(), "Below existential deposit");
+        }
+        Ok(amount)
+    }
+
+    /// Whether crediting `amount` to `target` would leave a dust account.
+    fn below_existential(target: &T::AccountId, amount: T::Balance) -> bool {
+        <balances::Module<T>>::total_balance(target).is_zero() && amount < <balances::Module<T>>::existential_deposit()
+    }
+
+    /// The first block of the current window of `length` blocks. A zero length means
+    /// a single window spanning the whole chain.
+    fn window_start(length: T::BlockNumber) -> T::BlockNumber {
+        let now = <system::Module<T>>::block_number();
+        if length.is_zero() { Zero::zero() } else { now - now % length }
+    }
+
+    /// Count a finalized deposit, and each of the authorities who signed it, towards
+    /// the current participation window.
+    fn note_participation(signers: &[T::AccountId]) {
+        let start = Self::window_start(Self::participation_window_length());
+        let (last_start, finalized) = Self::finalized_in_window();
+        let finalized = if last_start == start { finalized + 1 } else { 1 };
+        <FinalizedInWindow<T>>::put((start, finalized));
+
+        for signer in signers {
+            let (last_start, signed) = Self::signed_in_window(signer);
+            let signed = if last_start == start { signed + 1 } else { 1 };
+            <SignedInWindow<T>>::insert(signer, (start, signed));
+        }
+    }
+
+    /// The authorities with the stake each signs with, largest first. Authorities with
+    /// equal stake keep their order in the set.
+    pub fn authorities_by_stake() -> Vec<(T::AccountId, T::Balance)> {
+        let mut ranked = Self::authorities().into_iter()
+            .map(|a| {
+                let stake = <balances::Module<T>>::total_balance(&a);
+                (a, stake)
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1));
+        ranked
+    }
+
+    /// The percentage of deposits finalized by signing in the current participation
+    /// window that `who` signed, if they are an authority and any were finalized.
+    pub fn authority_participation_rate(who: &T::AccountId) -> Option<u32> {
+        if !Self::authorities().iter().any(|a| a == who) {
+            return None;
+        }
+
+        let start = Self::window_start(Self::participation_window_length());
+        let (last_start, finalized) = Self::finalized_in_window();
+        if last_start != start || finalized == 0 {
+            return None;
+        }
+
+        let (signed_start, signed) = Self::signed_in_window(who);
+        let signed = if signed_start == start { signed } else { 0 };
+        Some(((signed as u64) * 100 / (finalized as u64)) as u32)
+    }
+
+    /// The start of the current withdraw window and the number of withdraws `who`
+    /// will have made in it after one more, failing if that would exceed the
+    /// per-account limit.
+    fn next_withdraw_in_window(who: &T::AccountId) -> rstd::result::Result<(T::BlockNumber, u32), &'static str> {
+        let start = Self::window_start(Self::withdraw_window_length());
+        let (last_start, count) = Self::withdraws_in_window(who);
+        let count = if last_start == start { count + 1 } else { 1 };
+        if let Some(max) = Self::max_withdraws_per_account_per_window() {
+            ensure!(count <= max, "Too many withdraws in window");
+        }
+
+        Ok((start, count))
+    }
+
+    /// Whether the given signers are enough to approve a request. With a count
+    /// threshold configured only the number of signers matters, otherwise their
+    /// stake must clear a supermajority. Nothing is ever approved by stake while the
+    /// total stake is zero, which would otherwise leave the threshold comparison
+    /// dividing by zero.
+    fn is_approved(signer_count: usize, stake_sum: T::Balance) -> bool {
+        if let Some(threshold) = Self::count_threshold() {
+            return signer_count >= threshold as usize;
+        }
+
+        let total = Self::approval_total();
+        if total.is_zero() {
+            return false;
+        }
+        // Signers who have since left the authority set can outweigh the current set
+        if stake_sum >= total {
+            return true;
+        }
+
+        VoteThreshold::SuperMajorityApprove.approved(stake_sum, total - stake_sum, total, total)
+    }
+
+    /// Sum signers' stakes, saturating at the largest balance rather than wrapping, so
+    /// an overflowing sum can never fall below the threshold it is compared with.
+    pub(crate) fn sum_stakes<I: Iterator<Item = T::Balance>>(stakes: I) -> T::Balance {
+        stakes.fold(Zero::zero(), |a: T::Balance, b| a.checked_add(&b).unwrap_or_else(T::Balance::max_value))
+    }
+
+    /// The stake, or quadratic weight, that signers' contributions are measured against.
+    fn approval_total() -> T::Balance {
+        // Quadratic weights are only comparable with each other, so the threshold is
+        // taken over the authorities' combined weight rather than the total issuance
+        if Self::quadratic_approval() {
+            Self::sum_stakes(Self::authorities().iter()
+                .map(|a| Self::approval_weight(<balances::Module<T>>::total_balance(a))))
+        } else {
+            <balances::Module<T>>::total_issuance()
+        }
+    }
+
+    /// The smallest combined signing stake that currently approves a request, just
+    /// over half of the total stake. Under quadratic approval this is in square-root
+    /// weight. A deposit with a committee is instead measured against the committee's
+    /// stake. `None` while a count threshold applies instead, or while there is no
+    /// stake at all and nothing can be approved.
+    pub fn effective_threshold_stake() -> Option<T::Balance> {
+        if Self::count_threshold().is_some() {
+            return None;
+        }
+
+        let total = Self::approval_total();
+        if total.is_zero() {
+            return None;
+        }
+        Some(total / <T::Balance as As<u64>>::sa(2) + <T::Balance as As<u64>>::sa(1))
+    }
+
+    /// The weight a signer with `stake` carries towards approval. Under quadratic
+    /// approval this is the integer square root of the stake, rounded down, so an
+    /// authority with four times the stake of another only carries twice the weight.
+    fn approval_weight(stake: T::Balance) -> T::Balance {
+        if Self::quadratic_approval() {
+            stake.integer_sqrt()
+        } else {
+            stake
+        }
+    }
 }
 
 impl<X, T> session::OnSessionChange<X> for Module<T> where T: Trait, T: session::Trait {
     fn on_session_change(_: X, _: bool) {
-        let next_authorities = <session::Module<T>>::validators()
-            .into_iter()
-            .collect::<Vec<T::AccountId>>();
+        // A set installed by governance is not replaced by the session validators
+        if Self::governance_authorities() {
+            return;
+        }
+
+        // Validators whose authority key was migrated sign with the new key
+        let mut next_authorities = Vec::new();
+        for validator in <session::Module<T>>::validators() {
+            let authority = Self::migrated_authority(validator);
+            if !next_authorities.contains(&authority) {
+                next_authorities.push(authority);
+            }
+        }
 
         // instant changes
         let last_authorities = <Authorities<T>>::get();
         if next_authorities != last_authorities {
-            <Authorities<T>>::put(next_authorities.clone());
-            Self::deposit_event(RawEvent::NewAuthorities(next_authorities));
+            Self::set_authorities(next_authorities);
         }
     }
 }
@@ -246,13 +1608,37 @@ impl<X, T> session::OnSessionChange<X> for Module<T> where T: Trait, T: session:
 decl_event!(
     pub enum Event<T> where <T as system::Trait>::Hash,
                             <T as system::Trait>::AccountId,
+                            <T as system::Trait>::BlockNumber,
                             <T as balances::Trait>::Balance {
-        // Deposit event for an account, an eligible blockchain transaction hash, and quantity
-        Deposit(AccountId, Hash, Balance),
-        // Withdraw event for an account, and an amount
-        Withdraw(AccountId, Balance),
+        // Deposit event for an account, an eligible blockchain transaction hash and log index, and quantity
+        Deposit(AccountId, Hash, LogIndex, Balance),
+        // Withdraw event for an account, an amount, and the global message nonce
+        Withdraw(AccountId, Balance, u64),
         // New authority set has been applied.
         NewAuthorities(Vec<AccountId>),
+        // Authority set changed, with the new set id, trie root of the members, and member count
+        AuthoritySetChanged(u64, Hash, u32),
+        // Withdraw failed on the eligible blockchain and was cancelled, refunding the account
+        WithdrawFailed(AccountId, Hash, Balance),
+        // Scheduled authority rotation was applied at a block, producing the given set id
+        ScheduledRotationApplied(BlockNumber, u64),
+        // Deposit finalized by the trusted relayer without the signing threshold (non-production only),
+        // with the global message nonce
+        InstantFinalized(AccountId, Hash, LogIndex, Balance, u64),
+        // Deposit finalized by authority signatures, with the global message nonce
+        DepositFinalized(AccountId, Hash, LogIndex, Balance, u64),
+        // Withdraw cancelled after not being approved by its expiry
+        WithdrawExpired(AccountId, Hash, Balance),
+        // Approved deposit rejected because it could not be credited
+        DepositRejected(Hash, LogIndex),
+        // Approved deposit held for review until the given block
+        DepositHeldForReview(Hash, LogIndex, BlockNumber),
+        // Deposit intent removed after no authority attested to it in time
+        DepositIntentExpired(Hash, LogIndex),
+        // Account credited by governance outside the deposit flow
+        ManualCredit(AccountId, Balance),
+        // Withdraw in a batch that an authority could not sign, with the reason
+        WithdrawSignatureFailed(AccountId, Hash, Vec<u8>),
     }
 );
 
@@ -264,15 +1650,56 @@ decl_storage! {
 
         /// The active set of bridge authorities who can sign off on requests
         pub Authorities get(authorities) config(): Vec<T::AccountId>;
+        /// Number of times the authority set has changed since genesis
+        pub AuthoritySetId get(authority_set_id): u64;
+        /// Nonce of the next cross-chain message, shared by deposit finalizations and
+        /// withdraw requests so the counterpart chain can process them in order
+        pub MessageNonce get(message_nonce): u64;
+        /// Trie root of the current authority set members, unset for the genesis set
+        pub AuthoritySetRoot get(authority_set_root): T::Hash;
+        /// Authority set to swap in at the end of the given block
+        pub ScheduledAuthorities get(scheduled_authorities): Option<(T::BlockNumber, Vec<T::AccountId>)>;
+        /// Operator-supplied metadata for each authority
+        pub AuthorityMeta get(authority_metadata): map T::AccountId => Vec<u8>;
+        /// Whether the authorities were installed by governance rather than taken from
+        /// the session validators, and so are kept across session changes
+        pub GovernanceAuthorities get(governance_authorities): bool;
+        /// Blocks a proposed authority key migration must wait before it can be executed
+        pub AuthorityMigrationDelay get(authority_migration_delay): T::BlockNumber;
+        /// Proposed migrations from an authority key to its replacement, with the block
+        /// from which each can be executed
+        pub PendingAuthorityMigration get(pending_authority_migration): map T::AccountId => Option<(T::AccountId, T::BlockNumber)>;
+        /// Key each authority migrated to, substituted for them among the session validators
+        pub MigratedAuthorityKey get(migrated_authority_key): map T::AccountId => Option<T::AccountId>;
 
         /// Number of deposits
         pub DepositCount get(deposit_count): u32;
         /// List of all deposit requests on Edgeware taken to be the transaction hash
-        /// from the eligible blockchain
-        pub Deposits get(deposits): Vec<T::Hash>;
-        /// Mapping of deposit transaction hashes from the eligible blockchain to the
-        /// deposit request record
-        pub DepositOf get(deposit_of): map T::Hash => Option<(DepositIndex, T::AccountId, T::Balance, Vec<T::AccountId>, bool)>;
+        /// and log index from the eligible blockchain
+        pub Deposits get(deposits): Vec<(T::Hash, LogIndex)>;
+        /// Mapping of deposit transaction hashes and log indices from the eligible
+        /// blockchain to the deposit request record
+        pub DepositOf get(deposit_of): map (T::Hash, LogIndex) => Option<DepositRecord<T::AccountId, T::Balance>>;
+        /// Block at which each deposit record was created
+        pub DepositCreatedAt get(deposit_created_at): map (T::Hash, LogIndex) => T::BlockNumber;
+        /// Blocks a deposit intent may wait for its first attestation before it expires
+        pub DepositIntentExpiry get(deposit_intent_expiry): T::BlockNumber;
+        /// Deposit intents due to expire at the end of each block
+        pub DepositIntentsExpiringAt get(deposit_intents_expiring_at): map T::BlockNumber => Vec<(T::Hash, LogIndex)>;
+        /// Receipt for each finalized deposit
+        pub DepositReceiptOf get(deposit_receipt): map (T::Hash, LogIndex) => Option<DepositReceipt<T::Hash, T::Balance, T::BlockNumber>>;
+        /// Block at which each authority signed each deposit
+        pub DepositSignedAt get(deposit_signed_at): map ((T::Hash, LogIndex), T::AccountId) => T::BlockNumber;
+        /// Per mille of an authority's stake lost for each block they wait before signing a deposit
+        pub ApprovalDecayPerBlock get(approval_decay_per_block): u32;
+        /// Maximum per mille of an authority's stake that can be lost to approval decay
+        pub ApprovalDecayMax get(approval_decay_max): u32;
+        /// Ed25519 key of the oracle that must co-sign deposits, if any
+        pub OracleKey get(oracle_key): Option<H256>;
+        /// Whether the oracle has confirmed each deposit
+        pub OracleConfirmed get(oracle_confirmed): map (T::Hash, LogIndex) => bool;
+        /// Whether deposits keyed by transaction hash alone have been moved to log index keys
+        pub DepositKeysMigrated get(deposit_keys_migrated) build(|_| true): bool;
         
         /// Number of withdraws
         pub WithdrawCount get(withdraw_count): u32;
@@ -280,8 +1707,99 @@ decl_storage! {
         /// on Edgeware with the user's account, quantity, and nonce
         pub Withdraws get(withdraws): Vec<T::Hash>;
         /// Mapping of withdraw record hashes to the record
-        pub WithdrawOf get(withdraw_of): map T::Hash => Option<(WithdrawIndex, T::AccountId, T::Balance, Vec<(T::AccountId, Vec<u8>)>, bool)>;
+        pub WithdrawOf get(withdraw_of): map T::Hash => Option<WithdrawRecord<T::AccountId, T::Balance>>;
         /// Nonce for creating unique hashes per user per withdraw request
         pub WithdrawNonceOf get(withdraw_nonce_of): map T::AccountId => u32;
+        /// Furthest ahead, in blocks, that a withdraw may set its expiry, zero for no limit
+        pub MaxWithdrawExpiry get(max_withdraw_expiry): T::BlockNumber;
+        /// Withdraws due to expire at the end of each block
+        pub WithdrawsExpiringAt get(withdraws_expiring_at): map T::BlockNumber => Vec<T::Hash>;
+        /// Whether a withdraw was cancelled for passing its expiry unapproved
+        pub WithdrawExpired get(withdraw_expired): map T::Hash => bool;
+        /// Block at the end of which a withdraw is cancelled if still unapproved, if any
+        pub WithdrawExpiresAt get(withdraw_expires_at): map T::Hash => Option<T::BlockNumber>;
+        /// Start of the withdraw window each pending withdraw was counted in
+        pub WithdrawCountedInWindow get(withdraw_counted_in_window): map T::Hash => Option<T::BlockNumber>;
+        /// Account refunded if a withdraw fails, when other than the withdrawing account
+        pub WithdrawRefundTo get(withdraw_refund_to): map T::Hash => Option<T::AccountId>;
+        /// Authorities attesting that a withdraw failed on the eligible blockchain
+        pub WithdrawFailureAttesters get(withdraw_failure_attesters): map T::Hash => Vec<T::AccountId>;
+        /// Whether a withdraw was cancelled after failing on the eligible blockchain
+        pub WithdrawFailed get(withdraw_failed): map T::Hash => bool;
+
+        /// Number of authority signatures required to approve a request, overriding the
+        /// stake-weighted threshold when set
+        pub CountThreshold get(count_threshold): Option<u32>;
+        /// Total quantity of each account's withdraws awaiting approval. The balance is
+        /// only burned on approval, so this is informational and locks nothing.
+        pub WithdrawReserved get(withdraw_reserved): map T::AccountId => T::Balance;
+        /// Every credit made by governance through `force_credit`
+        pub ManualInterventions get(manual_interventions): Vec<ManualIntervention<T::AccountId, T::Balance, T::BlockNumber>>;
+        /// Ed25519 group key of the off-chain threshold-signature committee, if any
+        pub CommitteeGroupKey get(committee_group_key): Option<H256>;
+        /// Approved deposits that could not be credited, awaiting governance
+        pub DepositRejected get(deposit_rejected): map (T::Hash, LogIndex) => bool;
+        /// Quantity from which approved deposits are held for review, zero to disable
+        pub ReviewThreshold get(review_threshold): T::Balance;
+        /// Blocks a deposit held for review waits before it is released automatically
+        pub ReviewDelay get(review_delay): T::BlockNumber;
+        /// Approved deposits held for review, with the block they are released at
+        pub ReviewQueue get(review_queue): map (T::Hash, LogIndex) => Option<T::BlockNumber>;
+        /// Deposits due to be released from review at the end of each block
+        pub ReviewReleasesAt get(review_releases_at): map T::BlockNumber => Vec<(T::Hash, LogIndex)>;
+        /// Whether signers are weighted by the square root of their stake
+        pub QuadraticApproval get(quadratic_approval): bool;
+        /// Number of authorities sampled to sign each deposit, zero for all of them
+        pub CommitteeSize get(committee_size): u32;
+        /// Seed mixed into each deposit's committee sample
+        pub CommitteeSeed get(committee_seed): T::Hash;
+
+        /// Number of deposit, signing and finalization extrinsics submitted by each account
+        pub RelayerSubmitCount get(relayer_submit_count): map T::AccountId => u32;
+
+        /// Length in blocks of the window over which authority participation is measured
+        pub ParticipationWindowLength get(participation_window_length): T::BlockNumber;
+        /// Start of the participation window, and the total finalization latency and number
+        /// of deposits finalized in it
+        pub LatencyInWindow get(latency_in_window): (T::BlockNumber, u64, u32);
+        /// Start of the participation window and the deposits finalized by signing in it
+        pub FinalizedInWindow get(finalized_in_window): (T::BlockNumber, u32);
+        /// Start of the participation window each authority last signed a finalized deposit
+        /// in, and how many finalized deposits they signed there
+        pub SignedInWindow get(signed_in_window): map T::AccountId => (T::BlockNumber, u32);
+
+        /// Whether all bridge operations are halted
+        pub Paused get(paused): bool;
+        /// Whether deposits and their signing are halted
+        pub DepositsPaused get(deposits_paused): bool;
+        /// Whether withdraws and their signing are halted
+        pub WithdrawsPaused get(withdraws_paused): bool;
+
+        /// Whether authorities are prevented from creating deposit records themselves
+        pub RequireIntentBeforeAttestation get(require_intent_before_attestation): bool;
+        /// Whether repeated deposit signatures by the same authority succeed as no-ops
+        pub IdempotentAttestations get(idempotent_attestations): bool;
+
+        /// Whether deposits too small to create their target account are escrowed rather than rejected
+        pub EscrowBelowExistential get(escrow_below_existential): bool;
+        /// Approved deposits held for accounts that did not exist when they were credited
+        pub EscrowedDepositOf get(escrowed_deposit_of): map T::AccountId => T::Balance;
+
+        /// Smallest quantity that can be withdrawn at once
+        pub MinWithdraw get(min_withdraw): T::Balance;
+        /// Largest quantity that can be withdrawn at once, or zero for no limit
+        pub MaxWithdraw get(max_withdraw): T::Balance;
+
+        /// Length in blocks of the window over which per-account withdraws are counted
+        pub WithdrawWindowLength get(withdraw_window_length): T::BlockNumber;
+        /// Maximum number of withdraws a single account may make within one window
+        pub MaxWithdrawsPerAccountPerWindow get(max_withdraws_per_account_per_window): Option<u32>;
+        /// Start of the window each account last withdrew in and how many withdraws it made there
+        pub WithdrawsInWindow get(withdraws_in_window): map T::AccountId => (T::BlockNumber, u32);
+
+        /// Whether the trusted relayer may finalize deposits without the signing threshold
+        pub InstantFinalizeEnabled get(instant_finalize_enabled): bool;
+        /// The relayer allowed to instantly finalize deposits when enabled
+        pub TrustedRelayer get(trusted_relayer): Option<T::AccountId>;
     }
 }
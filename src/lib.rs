@@ -22,9 +22,9 @@ extern crate serde;
 // Needed for deriving `Serialize` and `Deserialize` for various types.
 // We only implement the serde traits for std builds - they're unneeded
 // in the wasm runtime.
-// #[cfg(feature = "std")]
-// #[macro_use]
-// extern crate serde_derive;
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate serde_derive;
 // #[cfg(test)]
 // #[macro_use]
 // extern crate hex_literal;
@@ -61,9 +61,11 @@ mod tests {
     use super::*;
     use runtime_io::with_externalities;
     use system::{EventRecord, Phase};
-    use primitives::{H256, Blake2Hasher, Hasher};
+    use primitives::{H256, H512, Blake2Hasher, Hasher, ed25519};
     use runtime_primitives::{BuildStorage};
-    use runtime_primitives::traits::{BlakeTwo256, Identity};
+    use runtime_support::StorageMap;
+    use codec::{Decode, Encode};
+    use runtime_primitives::traits::{BlakeTwo256, Hash, Identity, OnFinalise};
     use runtime_primitives::testing::{Digest, DigestItem, Header};
 
     impl_outer_origin! {
@@ -137,13 +139,21 @@ mod tests {
     // This function basically just builds a genesis storage key/value store according to
     // our desired mockup.
     fn new_test_ext() -> sr_io::TestExternalities<Blake2Hasher> {
+        new_test_ext_with_balances([(1, 10000), (2, 10000), (3, 10000), (4, 100), (5, 100), (6, 100)].to_vec())
+    }
+
+    fn new_test_ext_with_balances(balances: Vec<(u64, u64)>) -> sr_io::TestExternalities<Blake2Hasher> {
+        new_test_ext_with_config(balances, 0)
+    }
+
+    fn new_test_ext_with_config(balances: Vec<(u64, u64)>, existential_deposit: u64) -> sr_io::TestExternalities<Blake2Hasher> {
         let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
         // // We use default for brevity, but you can configure as desired if needed.
         t.extend(balances::GenesisConfig::<Test>{
-            balances: [(1, 10000), (2, 10000), (3, 10000), (4, 100), (5, 100), (6, 100)].to_vec(),
+            balances: balances,
             transaction_base_fee: 0,
             transaction_byte_fee: 0,
-            existential_deposit: 0,
+            existential_deposit: existential_deposit,
             transfer_fee: 0,
             creation_fee: 0,
             reclaim_rebate: 0,
@@ -154,16 +164,28 @@ mod tests {
         t.into()
     }
 
+    // Deterministic xorshift generator so property-style tests are reproducible.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
     fn deposit(who: u64, target: u64, transaction_hash: H256, quantity: u64) -> super::Result {
-        Bridge::deposit(Origin::signed(who), target, transaction_hash, quantity)
+        Bridge::deposit(Origin::signed(who), target, transaction_hash, 0, quantity)
     }
 
     fn sign_deposit(who: u64, target: u64, transaction_hash: H256, quantity: u64) -> super::Result {
-        Bridge::sign_deposit(Origin::signed(who), target, transaction_hash, quantity)
+        Bridge::sign_deposit(Origin::signed(who), target, transaction_hash, 0, quantity)
     }
 
     fn withdraw(who: u64, quantity: u64, signed_cross_chain_tx: &[u8]) -> super::Result {
-        Bridge::withdraw(Origin::signed(who), quantity, signed_cross_chain_tx.to_vec())
+        Bridge::withdraw(Origin::signed(who), quantity, signed_cross_chain_tx.to_vec(), None, None)
     }
 
     fn sign_withdraw(who: u64, target: u64, record_hash: H256, quantity: u64, signed_cross_chain_tx: &[u8]) -> super::Result {
@@ -184,6 +206,185 @@ mod tests {
         });
     }
 
+    #[test]
+    fn bridge_version_should_be_exposed() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_eq!(Bridge::bridge_version(), bridge::BRIDGE_VERSION);
+        });
+    }
+
+    #[test]
+    fn session_change_should_emit_authority_set_changed() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_eq!(Bridge::authority_set_id(), 0);
+
+            Session::set_validators(&[1, 2]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            let root = BlakeTwo256::enumerated_trie_root(&[&1u64.encode()[..], &2u64.encode()[..]]);
+            assert_eq!(Bridge::authorities(), vec![1, 2]);
+            assert_eq!(Bridge::authority_set_id(), 1);
+            assert_eq!(Bridge::authority_set_root(), root);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::AuthoritySetChanged(1, root, 2)));
+
+            // An unchanged set does not advance the set id
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authority_set_id(), 1);
+
+            Session::set_validators(&[1, 2, 3]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authority_set_id(), 2);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::AuthoritySetChanged(2, Bridge::authorities_root(&[1, 2, 3]), 3)));
+        });
+    }
+
+    #[test]
+    fn scheduled_authority_set_should_activate_at_block() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert!(Bridge::schedule_authority_set(Origin::signed(1), vec![4, 5], 5).is_err());
+            assert_eq!(Bridge::schedule_authority_set(Origin::ROOT, vec![], 5), Err("Invalid empty authority set"));
+            assert_eq!(Bridge::schedule_authority_set(Origin::ROOT, vec![4, 5], 1), Err("Activation must be in the future"));
+            assert_eq!(Bridge::schedule_authority_set(Origin::ROOT, vec![4, 5, 4], 5), Err("Duplicate authority"));
+            assert_eq!(Bridge::scheduled_authorities(), None);
+            assert_ok!(Bridge::schedule_authority_set(Origin::ROOT, vec![4, 5], 5));
+
+            <Bridge as OnFinalise<u64>>::on_finalise(4);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3]);
+            <Bridge as OnFinalise<u64>>::on_finalise(5);
+            assert_eq!(Bridge::authorities(), vec![4, 5]);
+            assert_eq!(Bridge::scheduled_authorities(), None);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::ScheduledRotationApplied(5, 1)));
+        });
+    }
+
+    #[test]
+    fn scheduled_authority_set_should_survive_session_change() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            Session::set_validators(&[1, 2, 3]);
+            assert_ok!(Bridge::schedule_authority_set(Origin::ROOT, vec![4, 5], 5));
+            <Bridge as OnFinalise<u64>>::on_finalise(5);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::ScheduledRotationApplied(5, 1)));
+
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![4, 5]);
+            assert_eq!(Bridge::authority_set_id(), 1);
+
+            // Once released, the session validators take over again
+            assert!(Bridge::release_authorities_to_session(Origin::signed(4)).is_err());
+            assert_ok!(Bridge::release_authorities_to_session(Origin::ROOT));
+            assert_eq!(Bridge::release_authorities_to_session(Origin::ROOT), Err("Authorities already follow session"));
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn cancelled_authority_set_should_not_activate() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_eq!(Bridge::cancel_scheduled_authority_set(Origin::ROOT), Err("No scheduled rotation"));
+            assert_ok!(Bridge::schedule_authority_set(Origin::ROOT, vec![4, 5], 5));
+            assert_ok!(Bridge::cancel_scheduled_authority_set(Origin::ROOT));
+            <Bridge as OnFinalise<u64>>::on_finalise(5);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3]);
+            assert_eq!(Bridge::authority_set_id(), 0);
+        });
+    }
+
+    #[test]
+    fn set_authority_metadata_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Bridge::set_authority_metadata(Origin::signed(1), b"alice https://example.org".to_vec()));
+            assert_eq!(Bridge::authority_metadata(1), b"alice https://example.org".to_vec());
+            assert_eq!(Bridge::set_authority_metadata(Origin::signed(4), b"mallory".to_vec()), Err("Invalid non-authority sender"));
+            assert_eq!(Bridge::set_authority_metadata(Origin::signed(1), vec![0; bridge::MAX_AUTHORITY_METADATA_LEN + 1]),
+                       Err("Metadata too long"));
+            assert_eq!(Bridge::authority_metadata(1), b"alice https://example.org".to_vec());
+        });
+    }
+
+    #[test]
+    fn authority_migration_should_execute_after_delay() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_eq!(Bridge::propose_authority_migration(Origin::signed(1), 7), Err("No migration delay configured"));
+            assert_eq!(Bridge::set_authority_migration_delay(Origin::ROOT, 0), Err("Invalid zero migration delay"));
+            assert_ok!(Bridge::set_authority_migration_delay(Origin::ROOT, 10));
+            assert_eq!(Bridge::propose_authority_migration(Origin::signed(4), 7), Err("Invalid non-authority sender"));
+            assert_eq!(Bridge::propose_authority_migration(Origin::signed(1), 2), Err("New key is already an authority"));
+            assert_ok!(Bridge::propose_authority_migration(Origin::signed(1), 7));
+            assert_eq!(Bridge::propose_authority_migration(Origin::signed(1), 8), Err("Migration already pending"));
+
+            System::set_block_number(10);
+            assert_eq!(Bridge::execute_authority_migration(Origin::signed(4), 1, 7), Err("Migration still timelocked"));
+            assert_eq!(Bridge::execute_authority_migration(Origin::signed(4), 1, 8), Err("No pending migration"));
+
+            System::set_block_number(11);
+            assert_ok!(Bridge::execute_authority_migration(Origin::signed(4), 1, 7));
+            assert_eq!(Bridge::authorities(), vec![7, 2, 3]);
+            assert_eq!(Bridge::authority_set_id(), 1);
+            assert_eq!(Bridge::pending_authority_migration(1), None);
+
+            // The migrated key is not undone by the next session change
+            Session::set_validators(&[1, 2, 3]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![7, 2, 3]);
+            assert_eq!(Bridge::authority_set_id(), 1);
+
+            // The other authorities still follow the session validators
+            Session::set_validators(&[1, 2, 4]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![7, 2, 4]);
+            assert!(!Bridge::governance_authorities());
+        });
+    }
+
+    #[test]
+    fn authority_migrated_twice_should_follow_latest_key() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            Session::set_validators(&[1, 2, 3]);
+            assert_ok!(Bridge::set_authority_migration_delay(Origin::ROOT, 1));
+            assert_ok!(Bridge::propose_authority_migration(Origin::signed(1), 7));
+            System::set_block_number(2);
+            assert_ok!(Bridge::execute_authority_migration(Origin::signed(4), 1, 7));
+            assert_ok!(Bridge::propose_authority_migration(Origin::signed(7), 8));
+            System::set_block_number(3);
+            assert_ok!(Bridge::execute_authority_migration(Origin::signed(4), 7, 8));
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![8, 2, 3]);
+
+            // Migrating back to the original key does not loop
+            assert_ok!(Bridge::propose_authority_migration(Origin::signed(8), 1));
+            System::set_block_number(4);
+            assert_ok!(Bridge::execute_authority_migration(Origin::signed(4), 8, 1));
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn vetoed_authority_migration_should_not_execute() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_ok!(Bridge::set_authority_migration_delay(Origin::ROOT, 10));
+            assert_ok!(Bridge::propose_authority_migration(Origin::signed(1), 7));
+            assert!(Bridge::veto_authority_migration(Origin::signed(2), 1).is_err());
+            assert_ok!(Bridge::veto_authority_migration(Origin::ROOT, 1));
+            assert_eq!(Bridge::veto_authority_migration(Origin::ROOT, 1), Err("No pending migration"));
+
+            System::set_block_number(11);
+            assert_eq!(Bridge::execute_authority_migration(Origin::signed(4), 1, 7), Err("No pending migration"));
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3]);
+        });
+    }
+
     #[test]
     fn deposit_as_a_function_should_work() {
         with_externalities(&mut new_test_ext(), || {
@@ -193,7 +394,7 @@ mod tests {
             assert_eq!(System::events(), vec![
                 EventRecord {
                     phase: Phase::ApplyExtrinsic(0),
-                    event: Event::bridge(RawEvent::Deposit(5, hash, 10)),
+                    event: Event::bridge(RawEvent::Deposit(5, hash, 0, 10)),
                 }]
             );
         });
@@ -211,219 +412,1683 @@ mod tests {
     }
 
     #[test]
-    fn sign_deposit_as_bridge_authority_should_work() {
+    fn deposits_with_same_tx_and_different_log_index_should_not_collide() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            let hash = Blake2Hasher::hash(b"a sends money to b and c");
+            assert_ok!(Bridge::deposit(Origin::signed(5), 5, hash, 0, 10));
+            assert_ok!(Bridge::deposit(Origin::signed(6), 6, hash, 1, 20));
+            assert_eq!(Bridge::deposit(Origin::signed(6), 6, hash, 1, 20), Err("Deposit should not exist"));
+            assert_eq!(Bridge::deposits(), vec![(hash, 0), (hash, 1)]);
+
+            assert_ok!(Bridge::sign_deposit(Origin::signed(1), 6, hash, 1, 20));
+            assert_ok!(Bridge::sign_deposit(Origin::signed(2), 6, hash, 1, 20));
+            assert_eq!(Balances::total_balance(&6), 120);
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().completed, false);
+            assert_eq!(Balances::total_balance(&5), 100);
         });
     }
 
     #[test]
-    fn sign_deposit_supermajority_should_work() {
+    fn stuck_deposits_should_only_return_old_pending_deposits() {
         with_externalities(&mut new_test_ext(), || {
+            let (a, b, c) = (Blake2Hasher::hash(b"a"), Blake2Hasher::hash(b"b"), Blake2Hasher::hash(b"c"));
             System::set_block_number(1);
-            let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_deposit(1, 5, hash, quantity));
-            assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_deposit(2, 5, hash, quantity));
-            assert_eq!(Balances::total_balance(&5), 110);
+            assert_ok!(deposit(5, 5, a, 10));
+            System::set_block_number(3);
+            assert_ok!(deposit(5, 5, b, 10));
+            System::set_block_number(5);
+            assert_ok!(deposit(5, 5, c, 10));
+            assert_eq!(Bridge::deposit_created_at((b, 0)), 3);
+
+            System::set_block_number(10);
+            assert_eq!(Bridge::stuck_deposits(6), vec![(a, 0), (b, 0)]);
+            assert_eq!(Bridge::stuck_deposits(8), vec![(a, 0)]);
+            assert_eq!(Bridge::stuck_deposits(20), vec![]);
+
+            // Finalized deposits are never stuck
+            assert_ok!(sign_deposit(1, 5, a, 10));
+            assert_ok!(sign_deposit(2, 5, a, 10));
+            assert_eq!(Bridge::stuck_deposits(6), vec![(b, 0)]);
         });
     }
 
     #[test]
-    fn sign_deposit_once_complete_should_not_work() {
+    fn deposit_by_authority_without_intent_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_deposit(1, 5, hash, quantity));
-            assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_deposit(2, 5, hash, quantity));
-            assert_eq!(Balances::total_balance(&5), 110);
-            assert_eq!(sign_deposit(3, 5, hash, quantity),
-                       Err("Transaction already completed"));
+            assert_ok!(Bridge::set_require_intent_before_attestation(Origin::ROOT, true));
+            assert_eq!(deposit(1, 5, hash, 10), Err("Deposit intent required"));
+            assert_eq!(Bridge::deposit_of((hash, 0)), None);
+
+            // Once the user has registered the deposit authorities can attest
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
         });
     }
-    
+
     #[test]
-    fn sign_non_existent_deposit_as_bridge_authority_should_not_work() {
+    fn duplicate_sign_deposit_should_be_idempotent_when_enabled() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_eq!(sign_deposit(1, 5, hash, quantity), Err("Invalid transaction hash"));
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_eq!(sign_deposit(1, 5, hash, 10), Err("Invalid duplicate signings"));
+
+            assert_ok!(Bridge::set_idempotent_attestations(Origin::ROOT, true));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().signers, vec![1]);
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            // A retry that does not match the record is still an error
+            assert_eq!(sign_deposit(1, 5, hash, 11), Err("Quantities don't match"));
+            assert_eq!(sign_deposit(1, 6, hash, 10), Err("Accounts do not match"));
         });
     }
 
     #[test]
-    fn sign_deposit_with_wrong_quantity_should_not_work() {
+    fn deposit_by_authority_without_intent_requirement_should_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_eq!(sign_deposit(1, 5, hash, quantity - 1), Err("Quantities don't match"));
+            assert_ok!(deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().signers, vec![1]);
         });
     }
 
     #[test]
-    fn sign_deposit_with_wrong_target_should_not_work() {
-        with_externalities(&mut new_test_ext(), || {
+    fn authorities_by_stake_should_rank_descending() {
+        with_externalities(&mut new_test_ext_with_balances(vec![(1, 500), (2, 9000), (3, 500)]), || {
+            assert_eq!(Bridge::authorities_by_stake(), vec![(2, 9000), (1, 500), (3, 500)]);
+        });
+    }
+
+    #[test]
+    fn quadratic_approval_should_weaken_whale_authority() {
+        with_externalities(&mut new_test_ext_with_balances(vec![(1, 40000), (2, 10000), (3, 10000), (5, 100)]), || {
             System::set_block_number(1);
-            let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_eq!(sign_deposit(1, 4, hash, quantity), Err("Accounts do not match"));
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+
+            // Linearly the whale holds a supermajority of the issuance alone
+            assert_ok!(deposit(5, 5, a, 10));
+            assert_ok!(sign_deposit(1, 5, a, 10));
+            assert!(Bridge::deposit_of((a, 0)).unwrap().completed);
+
+            // Quadratically the whale weighs 200 of the authorities' 400
+            assert_ok!(Bridge::set_quadratic_approval(Origin::ROOT, true));
+            assert_ok!(deposit(5, 5, b, 10));
+            assert_ok!(sign_deposit(1, 5, b, 10));
+            assert!(!Bridge::deposit_of((b, 0)).unwrap().completed);
+            assert_ok!(sign_deposit(2, 5, b, 10));
+            assert!(Bridge::deposit_of((b, 0)).unwrap().completed);
         });
     }
 
     #[test]
-    fn sign_deposit_as_non_authority_should_not_work() {
+    fn flagged_deposit_should_wait_for_review() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_eq!(sign_deposit(5, 5, hash, quantity), Err("Invalid non-authority sender"));
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+            assert_ok!(Bridge::set_review(Origin::ROOT, 50, 5));
+
+            // Small deposits are credited as usual
+            assert_ok!(deposit(1, 5, a, 10));
+            assert_ok!(sign_deposit(2, 5, a, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
+
+            assert_ok!(deposit(1, 6, b, 50));
+            assert_ok!(sign_deposit(2, 6, b, 50));
+            assert_eq!(Balances::total_balance(&6), 100);
+            assert_eq!(Bridge::review_queue((b, 0)), Some(6));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::DepositHeldForReview(b, 0, 6)));
+            assert_eq!(sign_deposit(3, 6, b, 50), Err("Deposit under review"));
+
+            assert!(Bridge::release_reviewed(Origin::signed(1), b, 0).is_err());
+            assert_eq!(Bridge::release_reviewed(Origin::ROOT, a, 0), Err("Deposit not under review"));
+            assert_ok!(Bridge::release_reviewed(Origin::ROOT, b, 0));
+            assert_eq!(Balances::total_balance(&6), 150);
+            assert_eq!(Bridge::review_queue((b, 0)), None);
+            <bridge::ReviewQueue<Test>>::insert((b, 0), 6);
+            assert_eq!(Bridge::release_reviewed(Origin::ROOT, b, 0), Err("Transaction already completed"));
+            <bridge::ReviewQueue<Test>>::remove((b, 0));
+
+            // The scheduled release finds nothing left to credit
+            <Bridge as OnFinalise<u64>>::on_finalise(6);
+            assert_eq!(Balances::total_balance(&6), 150);
         });
     }
 
     #[test]
-    fn sign_deposit_twice_should_not_work() {
+    fn flagged_deposit_should_release_after_review_delay() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
             let hash = Blake2Hasher::hash(b"a sends money to b");
-            let quantity = 10;
-            assert_ok!(deposit(5, 5, hash, quantity));
-            assert_ok!(sign_deposit(1, 5, hash, quantity));
-            assert_eq!(sign_deposit(1, 5, hash, quantity), Err("Invalid duplicate signings"))
+            assert_ok!(Bridge::set_review(Origin::ROOT, 50, 5));
+            assert_ok!(deposit(1, 6, hash, 50));
+            assert_ok!(sign_deposit(2, 6, hash, 50));
+
+            <Bridge as OnFinalise<u64>>::on_finalise(5);
+            assert_eq!(Balances::total_balance(&6), 100);
+            <Bridge as OnFinalise<u64>>::on_finalise(6);
+            assert_eq!(Balances::total_balance(&6), 150);
+            assert!(Bridge::deposit_of((hash, 0)).unwrap().completed);
         });
     }
 
     #[test]
-    fn withdraw_as_a_function_should_work() {
+    fn effective_threshold_stake_should_follow_configuration() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let signed_tx = b"a sends money to b on Ethereum";
-            assert_ok!(withdraw(5, 10, signed_tx));
-            assert_eq!(System::events(), vec![
-                EventRecord {
-                    phase: Phase::ApplyExtrinsic(0),
-                    event: Event::bridge(RawEvent::Withdraw(5, 10)),
-                }]
-            );
+            // Just over half of the 30300 total issuance
+            assert_eq!(Bridge::effective_threshold_stake(), Some(15151));
+
+            assert_ok!(Bridge::set_quadratic_approval(Origin::ROOT, true));
+            assert_eq!(Bridge::effective_threshold_stake(), Some(151));
+            assert_ok!(Bridge::set_quadratic_approval(Origin::ROOT, false));
+
+            assert_ok!(Bridge::set_count_threshold(Origin::ROOT, Some(2)));
+            assert_eq!(Bridge::effective_threshold_stake(), None);
         });
     }
 
     #[test]
-    fn withdraw_with_not_enough_balance_not_work() {
-        with_externalities(&mut new_test_ext(), || {
+    fn effective_threshold_stake_should_be_exactly_enough() {
+        with_externalities(&mut new_test_ext_with_balances(vec![(1, 15150), (2, 1), (3, 15149), (5, 1)]), || {
             System::set_block_number(1);
-            let signed_tx = b"a sends money to b on Ethereum";
-            assert_eq!(Balances::total_balance(&4), 100);
-            assert_eq!(withdraw(4, 101, signed_tx), Err("Invalid balance for withdraw"));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_eq!(Bridge::effective_threshold_stake(), Some(15151));
+
+            // A non-authority registers the deposit so no stake is counted yet
+            assert_ok!(deposit(5, 3, hash, 10));
+            assert!(Bridge::deposit_of((hash, 0)).unwrap().signers.is_empty());
+
+            // One short of the threshold is not enough
+            assert_ok!(sign_deposit(1, 3, hash, 10));
+            assert!(!Bridge::deposit_of((hash, 0)).unwrap().completed);
+
+            // Reaching it exactly completes the deposit
+            assert_ok!(sign_deposit(2, 3, hash, 10));
+            assert!(Bridge::deposit_of((hash, 0)).unwrap().completed);
         });
     }
 
     #[test]
-    fn sign_withdraw_supermajority_should_work() {
+    fn force_credit_should_be_logged() {
         with_externalities(&mut new_test_ext(), || {
-            System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
-            let quantity = 10;
-            assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            assert_eq!(Balances::total_balance(&5), 100);
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
-            assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
-            assert_eq!(Balances::total_balance(&5), 100 - quantity);
+            System::set_block_number(3);
+            assert!(Bridge::force_credit(Origin::signed(1), 5, 10, b"stuck deposit".to_vec()).is_err());
+            assert_eq!(Bridge::force_credit(Origin::ROOT, 5, 10, vec![]), Err("Invalid empty reason"));
+            assert_ok!(Bridge::force_credit(Origin::ROOT, 5, 10, b"stuck deposit".to_vec()));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(Bridge::manual_interventions(), vec![bridge::ManualIntervention {
+                target: 5,
+                quantity: 10,
+                reason: b"stuck deposit".to_vec(),
+                block: 3,
+            }]);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::ManualCredit(5, 10)));
         });
     }
 
     #[test]
-    fn sign_withdraw_once_complete_should_not_work() {
+    fn deposit_committee_should_be_deterministic() {
         with_externalities(&mut new_test_ext(), || {
-            System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
-            let quantity = 10;
-            assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            assert_eq!(Balances::total_balance(&5), 100);
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
-            assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
-            assert_eq!(Balances::total_balance(&5), 100 - quantity);
-            assert_eq!(sign_withdraw(3, 5, hash, quantity, cross_chain_proof),
-                       Err("Transaction already completed"))
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+            assert_eq!(Bridge::deposit_committee(&(a, 0)), None);
+
+            assert_ok!(Bridge::set_committee(Origin::ROOT, 2, H256::from([7u8; 32])));
+            let committee = Bridge::deposit_committee(&(a, 0)).unwrap();
+            assert_eq!(committee.len(), 2);
+            assert_ne!(committee[0], committee[1]);
+            assert!(committee.iter().all(|id| Bridge::authorities().contains(id)));
+            assert_eq!(Bridge::deposit_committee(&(a, 0)), Some(committee));
+            assert_eq!(Bridge::deposit_committee(&(b, 0)), Bridge::deposit_committee(&(b, 0)));
+
+            assert_ok!(Bridge::set_committee(Origin::ROOT, 3, H256::from([7u8; 32])));
+            assert_eq!(Bridge::deposit_committee(&(a, 0)), None);
         });
     }
-    
+
     #[test]
-    fn sign_withdraw_with_wrong_quantity_should_not_work() {
+    fn sign_deposit_outside_committee_should_not_count() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
-            let quantity = 10;
-            assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_eq!(sign_withdraw(1, 5, hash, quantity - 1, cross_chain_proof), Err("Quantities don't match"));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(Bridge::set_committee(Origin::ROOT, 2, H256::from([7u8; 32])));
+            let committee = Bridge::deposit_committee(&(hash, 0)).unwrap();
+            let outsider = *Bridge::authorities().iter().find(|id| !committee.contains(id)).unwrap();
+
+            // An outsider creating the deposit is not recorded as a signer
+            assert_ok!(deposit(outsider, 5, hash, 10));
+            assert!(Bridge::deposit_of((hash, 0)).unwrap().signers.is_empty());
+            assert_eq!(sign_deposit(outsider, 5, hash, 10), Err("Invalid non-committee sender"));
+
+            // Half the committee's stake is not a supermajority of it
+            assert_ok!(sign_deposit(committee[0], 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(committee[1], 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
         });
     }
 
     #[test]
-    fn sign_withdraw_with_wrong_target_should_not_work() {
+    fn unattested_deposit_intent_should_expire() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
-            let quantity = 10;
-            assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_eq!(sign_withdraw(1, 4, hash, quantity, cross_chain_proof), Err("Accounts do not match"));
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+            assert_ok!(Bridge::set_deposit_intent_expiry(Origin::ROOT, 5));
+            assert_ok!(deposit(5, 5, a, 10));
+            assert_ok!(deposit(5, 5, b, 10));
+            assert_ok!(sign_deposit(1, 5, b, 10));
+
+            <Bridge as OnFinalise<u64>>::on_finalise(5);
+            assert!(Bridge::deposit_of((a, 0)).is_some());
+            <Bridge as OnFinalise<u64>>::on_finalise(6);
+            assert_eq!(Bridge::deposit_of((a, 0)), None);
+            assert_eq!(Bridge::deposits(), vec![(b, 0)]);
+            assert!(Bridge::deposit_of((b, 0)).is_some());
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::DepositIntentExpired(a, 0)));
         });
     }
 
     #[test]
-    fn sign_withdraw_as_non_authority_should_not_work() {
+    fn simulate_deposit_should_match_actual_effects() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
+            let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
-            assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_eq!(sign_withdraw(5, 5, hash, quantity, cross_chain_proof), Err("Invalid non-authority sender"));
+            use bridge::DepositSimOutcome::*;
+
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 5), Create);
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert!(Bridge::deposit_of((hash, 0)).is_some());
+
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 1), Append);
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 1), Error("Invalid duplicate signings"));
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity - 1, 2), Error("Quantities don't match"));
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 2), Finalize);
+            // Simulating changes nothing
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().signers, vec![1]);
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 110);
+
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 3), Error("Transaction already completed"));
+
+            // Exact retries are no-ops while attestations are idempotent
+            assert_ok!(Bridge::set_idempotent_attestations(Origin::ROOT, true));
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 1), Unchanged);
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity + 1, 1), Error("Quantities don't match"));
+            assert_eq!(sign_deposit(1, 5, hash, quantity + 1), Err("Quantities don't match"));
+
+            let hash = Blake2Hasher::hash(b"b sends money to c");
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Bridge::simulate_deposit(5, hash, 0, quantity, 1), Unchanged);
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().signers, vec![1]);
         });
     }
 
     #[test]
-    fn sign_withdraw_twice_should_not_work() {
+    fn authority_participation_rate_should_count_signed_deposits() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
-            let quantity = 10;
-            assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
-            assert_eq!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof), Err("Invalid duplicate signings"))
+            assert_ok!(Bridge::set_participation_window(Origin::ROOT, 100));
+            assert_eq!(Bridge::authority_participation_rate(&1), None);
+
+            let (a, b, c) = (Blake2Hasher::hash(b"a"), Blake2Hasher::hash(b"b"), Blake2Hasher::hash(b"c"));
+            assert_ok!(deposit(5, 5, a, 10));
+            assert_ok!(sign_deposit(3, 5, a, 10));
+            assert_ok!(sign_deposit(1, 5, a, 10));
+            for hash in [b, c].iter() {
+                assert_ok!(deposit(5, 5, *hash, 10));
+                assert_ok!(sign_deposit(1, 5, *hash, 10));
+                assert_ok!(sign_deposit(2, 5, *hash, 10));
+            }
+
+            assert_eq!(Bridge::authority_participation_rate(&1), Some(100));
+            assert_eq!(Bridge::authority_participation_rate(&2), Some(66));
+            assert_eq!(Bridge::authority_participation_rate(&3), Some(33));
+            assert_eq!(Bridge::authority_participation_rate(&5), None);
+
+            // Rates are only measured over the current window
+            System::set_block_number(100);
+            assert_eq!(Bridge::authority_participation_rate(&1), None);
         });
     }
 
     #[test]
-    fn sign_withdraw_with_non_existent_record_hash_should_not_work() {
+    fn finalized_deposit_should_have_receipt() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
-            let quantity = 10;
-            let hash = Blake2Hasher::hash(b"drew stone was here");
-            assert_eq!(sign_withdraw(1, 4, hash, quantity, cross_chain_proof), Err("Invalid record hash"));
-        });
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::deposit_receipt((hash, 0)), None);
+
+            System::set_block_number(2);
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_eq!(Bridge::deposit_receipt((hash, 0)), Some(bridge::DepositReceipt {
+                transaction_hash: hash,
+                log_index: 0,
+                quantity: 10,
+                finalized_at: 2,
+                latency: Some(1),
+            }));
+        });
+    }
+
+    fn oracle_sign(pair: &ed25519::Pair, target: u64, transaction_hash: H256, quantity: u64) -> H512 {
+        let message = Bridge::deposit_oracle_message(&target, transaction_hash, 0, quantity);
+        H512::from_slice(pair.sign(&message).as_ref())
+    }
+
+    #[test]
+    fn finalization_latency_should_be_recorded() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+            assert_ok!(Bridge::set_participation_window(Origin::ROOT, 100));
+            assert_eq!(Bridge::average_finalization_latency(), None);
+
+            assert_ok!(deposit(1, 5, a, 10));
+            assert_ok!(deposit(1, 5, b, 10));
+            System::set_block_number(5);
+            assert_ok!(sign_deposit(2, 5, a, 10));
+            assert_eq!(Bridge::deposit_receipt((a, 0)).unwrap().latency, Some(4));
+            System::set_block_number(8);
+            assert_ok!(sign_deposit(2, 5, b, 10));
+            assert_eq!(Bridge::deposit_receipt((b, 0)).unwrap().latency, Some(7));
+            assert_eq!(Bridge::average_finalization_latency(), Some(5));
+
+            // A deposit registered and finalized in one call leaves the average alone
+            let c = Blake2Hasher::hash(b"c sends money to d");
+            let committee = ed25519::Pair::from_seed(&[3u8; 32]);
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(H256::from_slice(committee.public().as_ref()))));
+            assert_ok!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, c, 0, 10, committee_sign(&committee, 5, c, 10)));
+            assert_eq!(Bridge::deposit_receipt((c, 0)).unwrap().latency, None);
+            assert_eq!(Bridge::average_finalization_latency(), Some(5));
+
+            // A new window starts without history
+            System::set_block_number(100);
+            assert_eq!(Bridge::average_finalization_latency(), None);
+        });
+    }
+
+    fn committee_sign(pair: &ed25519::Pair, target: u64, transaction_hash: H256, quantity: u64) -> H512 {
+        let message = Bridge::deposit_threshold_message(&target, transaction_hash, 0, quantity);
+        H512::from_slice(pair.sign(&message).as_ref())
+    }
+
+    #[test]
+    fn deposit_threshold_sig_should_finalize_with_group_signature() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+            let committee = ed25519::Pair::from_seed(&[3u8; 32]);
+            let signature = committee_sign(&committee, 5, a, 10);
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, a, 0, 10, signature),
+                       Err("No committee group key configured"));
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(H256::from_slice(committee.public().as_ref()))));
+
+            // A new deposit is registered and credited in one call
+            assert_ok!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, a, 0, 10, signature));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert!(Bridge::deposit_of((a, 0)).unwrap().completed);
+            assert_eq!(Bridge::deposits(), vec![(a, 0)]);
+            let events = System::events().into_iter().map(|r| r.event).collect::<Vec<_>>();
+            assert_eq!(events[events.len() - 2..].to_vec(), vec![
+                Event::bridge(RawEvent::Deposit(5, a, 0, 10)),
+                Event::bridge(RawEvent::DepositFinalized(5, a, 0, 10, 0)),
+            ]);
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, a, 0, 10, signature),
+                       Err("Transaction already completed"));
+
+            // A pending deposit is finalized without further authority signatures
+            assert_ok!(deposit(5, 5, b, 10));
+            assert_ok!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, b, 0, 10, committee_sign(&committee, 5, b, 10)));
+            assert_eq!(Balances::total_balance(&5), 120);
+        });
+    }
+
+    #[test]
+    fn deposit_threshold_sig_without_intent_should_not_work_when_required() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let committee = ed25519::Pair::from_seed(&[3u8; 32]);
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(H256::from_slice(committee.public().as_ref()))));
+            assert_ok!(Bridge::set_require_intent_before_attestation(Origin::ROOT, true));
+
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 10, committee_sign(&committee, 5, hash, 10)),
+                       Err("Deposit intent required"));
+            assert_eq!(Bridge::deposit_of((hash, 0)), None);
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            // Once the user has registered the deposit the committee can approve it
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 10, committee_sign(&committee, 5, hash, 10)));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn signatures_for_another_bridge_version_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let signer = ed25519::Pair::from_seed(&[3u8; 32]);
+            let key = H256::from_slice(signer.public().as_ref());
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(key)));
+            assert_ok!(Bridge::set_oracle_key(Origin::ROOT, Some(key)));
+            let version = bridge::BRIDGE_VERSION + 1;
+
+            let committee_message = (version, b"committee".to_vec(), hash, 0u32, 5u64, 10u64).encode();
+            let committee_sig = H512::from_slice(signer.sign(&committee_message).as_ref());
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 10, committee_sig),
+                       Err("Invalid committee signature"));
+
+            assert_ok!(deposit(5, 5, hash, 10));
+            let oracle_message = (version, b"oracle".to_vec(), hash, 0u32, 5u64, 10u64).encode();
+            let oracle_sig = H512::from_slice(signer.sign(&oracle_message).as_ref());
+            assert_eq!(Bridge::confirm_deposit_oracle(Origin::signed(6), hash, 0, oracle_sig),
+                       Err("Invalid oracle signature"));
+
+            // The same messages under the current version are accepted
+            assert_eq!(Bridge::deposit_oracle_message(&5, hash, 0, 10),
+                       (bridge::BRIDGE_VERSION, b"oracle".to_vec(), hash, 0u32, 5u64, 10u64).encode());
+            assert_ok!(Bridge::confirm_deposit_oracle(Origin::signed(6), hash, 0, oracle_sign(&signer, 5, hash, 10)));
+        });
+    }
+
+    #[test]
+    fn reviewed_deposit_should_not_be_released_while_paused() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(Bridge::set_review(Origin::ROOT, 50, 5));
+            assert_ok!(deposit(1, 6, hash, 50));
+            assert_ok!(sign_deposit(2, 6, hash, 50));
+            assert_eq!(Bridge::review_queue((hash, 0)), Some(6));
+
+            assert_ok!(Bridge::set_deposits_paused(Origin::ROOT, true));
+            <Bridge as OnFinalise<u64>>::on_finalise(6);
+            assert_eq!(Balances::total_balance(&6), 100);
+            assert_eq!(Bridge::review_releases_at(7), vec![(hash, 0)]);
+
+            assert_ok!(Bridge::set_deposits_paused(Origin::ROOT, false));
+            assert_ok!(Bridge::set_paused(Origin::ROOT, true));
+            <Bridge as OnFinalise<u64>>::on_finalise(7);
+            assert_eq!(Balances::total_balance(&6), 100);
+
+            assert_ok!(Bridge::set_paused(Origin::ROOT, false));
+            <Bridge as OnFinalise<u64>>::on_finalise(8);
+            assert_eq!(Balances::total_balance(&6), 150);
+            assert_eq!(Bridge::review_queue((hash, 0)), None);
+        });
+    }
+
+    #[test]
+    fn oracle_confirmation_should_not_credit_deposit_under_review() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let oracle = ed25519::Pair::from_seed(&[1u8; 32]);
+            assert_ok!(Bridge::set_review(Origin::ROOT, 50, 5));
+            assert_ok!(deposit(1, 6, hash, 50));
+            assert_ok!(sign_deposit(2, 6, hash, 50));
+            assert_eq!(Bridge::review_queue((hash, 0)), Some(6));
+
+            assert_ok!(Bridge::set_oracle_key(Origin::ROOT, Some(H256::from_slice(oracle.public().as_ref()))));
+            assert_ok!(Bridge::set_review(Origin::ROOT, 0, 5));
+            assert_eq!(Bridge::confirm_deposit_oracle(Origin::signed(4), hash, 0, oracle_sign(&oracle, 6, hash, 50)),
+                       Err("Deposit under review"));
+
+            // A deposit completed outside the queue is not credited again on release
+            assert_ok!(Bridge::set_instant_finalize(Origin::ROOT, true, 4));
+            <bridge::ReviewReleasesAt<Test>>::insert(9, vec![(hash, 0)]);
+            assert_ok!(Bridge::instant_finalize(Origin::signed(4), hash, 0));
+            <bridge::ReviewQueue<Test>>::insert((hash, 0), 9);
+            <Bridge as OnFinalise<u64>>::on_finalise(9);
+            assert_eq!(Balances::total_balance(&6), 150);
+            assert_eq!(Bridge::review_queue((hash, 0)), None);
+        });
+    }
+
+    #[test]
+    fn committee_approved_deposit_under_review_should_not_expire_as_intent() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let committee = ed25519::Pair::from_seed(&[3u8; 32]);
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(H256::from_slice(committee.public().as_ref()))));
+            assert_ok!(Bridge::set_deposit_intent_expiry(Origin::ROOT, 5));
+            assert_ok!(Bridge::set_review(Origin::ROOT, 50, 10));
+
+            assert_ok!(deposit(5, 5, hash, 50));
+            assert_ok!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 50, committee_sign(&committee, 5, hash, 50)));
+            assert_eq!(Bridge::review_queue((hash, 0)), Some(11));
+
+            <Bridge as OnFinalise<u64>>::on_finalise(6);
+            assert!(Bridge::deposit_of((hash, 0)).is_some());
+            assert_ok!(Bridge::release_reviewed(Origin::ROOT, hash, 0));
+            assert_eq!(Balances::total_balance(&5), 150);
+        });
+    }
+
+    #[test]
+    fn committee_finalization_should_not_count_towards_participation() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let a = Blake2Hasher::hash(b"a sends money to b");
+            let b = Blake2Hasher::hash(b"b sends money to c");
+            let committee = ed25519::Pair::from_seed(&[3u8; 32]);
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(H256::from_slice(committee.public().as_ref()))));
+            assert_ok!(Bridge::set_participation_window(Origin::ROOT, 100));
+
+            assert_ok!(deposit(1, 5, a, 10));
+            assert_ok!(sign_deposit(2, 5, a, 10));
+            assert_ok!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, b, 0, 10, committee_sign(&committee, 5, b, 10)));
+            assert_eq!(Bridge::authority_participation_rate(&1), Some(100));
+            assert_eq!(Bridge::authority_participation_rate(&3), Some(0));
+        });
+    }
+
+    #[test]
+    fn deposit_threshold_sig_with_invalid_signature_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let committee = ed25519::Pair::from_seed(&[3u8; 32]);
+            let imposter = ed25519::Pair::from_seed(&[4u8; 32]);
+            assert_ok!(Bridge::set_committee_group_key(Origin::ROOT, Some(H256::from_slice(committee.public().as_ref()))));
+
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 10, committee_sign(&imposter, 5, hash, 10)),
+                       Err("Invalid committee signature"));
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 20, committee_sign(&committee, 5, hash, 10)),
+                       Err("Invalid committee signature"));
+            // The oracle message is not accepted in place of the committee's
+            assert_eq!(Bridge::deposit_threshold_sig(Origin::signed(6), 5, hash, 0, 10, oracle_sign(&committee, 5, hash, 10)),
+                       Err("Invalid committee signature"));
+            assert_eq!(Bridge::deposit_of((hash, 0)), None);
+            assert_eq!(Balances::total_balance(&5), 100);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_oracle_key_should_wait_for_oracle() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            let oracle = ed25519::Pair::from_seed(&[1u8; 32]);
+            let imposter = ed25519::Pair::from_seed(&[2u8; 32]);
+            assert_ok!(Bridge::set_oracle_key(Origin::ROOT, Some(H256::from_slice(oracle.public().as_ref()))));
+
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            let bad_signature = oracle_sign(&imposter, 5, hash, quantity);
+            assert_eq!(Bridge::confirm_deposit_oracle(Origin::signed(4), hash, 0, bad_signature), Err("Invalid oracle signature"));
+            let wrong_quantity = oracle_sign(&oracle, 5, hash, quantity + 1);
+            assert_eq!(Bridge::confirm_deposit_oracle(Origin::signed(4), hash, 0, wrong_quantity), Err("Invalid oracle signature"));
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            assert_ok!(Bridge::confirm_deposit_oracle(Origin::signed(4), hash, 0, oracle_sign(&oracle, 5, hash, quantity)));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert!(Bridge::deposit_of((hash, 0)).unwrap().completed);
+        });
+    }
+
+    #[test]
+    fn oracle_confirmation_before_threshold_should_finalize_on_signing() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            let oracle = ed25519::Pair::from_seed(&[1u8; 32]);
+            assert_eq!(Bridge::confirm_deposit_oracle(Origin::signed(4), hash, 0, oracle_sign(&oracle, 5, hash, quantity)),
+                       Err("No oracle configured"));
+            assert_ok!(Bridge::set_oracle_key(Origin::ROOT, Some(H256::from_slice(oracle.public().as_ref()))));
+
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(Bridge::confirm_deposit_oracle(Origin::signed(4), hash, 0, oracle_sign(&oracle, 5, hash, quantity)));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_as_bridge_authority_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_supermajority_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_once_complete_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(sign_deposit(3, 5, hash, quantity),
+                       Err("Transaction already completed"));
+        });
+    }
+    
+    #[test]
+    fn sign_non_existent_deposit_as_bridge_authority_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_eq!(sign_deposit(1, 5, hash, quantity), Err("Invalid transaction hash"));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_wrong_quantity_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_eq!(sign_deposit(1, 5, hash, quantity - 1), Err("Quantities don't match"));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_wrong_target_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_eq!(sign_deposit(1, 4, hash, quantity), Err("Accounts do not match"));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_as_non_authority_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_eq!(sign_deposit(5, 5, hash, quantity), Err("Invalid non-authority sender"));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_twice_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(sign_deposit(1, 5, hash, quantity), Err("Invalid duplicate signings"))
+        });
+    }
+
+    #[test]
+    fn instant_finalize_by_trusted_relayer_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(Bridge::set_instant_finalize(Origin::ROOT, true, 6));
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(Bridge::instant_finalize(Origin::signed(6), hash, 0));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::InstantFinalized(5, hash, 0, quantity, 0)));
+            assert_eq!(Bridge::instant_finalize(Origin::signed(6), hash, 0), Err("Transaction already completed"));
+        });
+    }
+
+    #[test]
+    fn instant_finalize_when_disabled_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_eq!(Bridge::instant_finalize(Origin::signed(6), hash, 0), Err("Instant finalization disabled"));
+            assert_ok!(Bridge::set_instant_finalize(Origin::ROOT, true, 6));
+            assert_ok!(Bridge::set_instant_finalize(Origin::ROOT, false, 6));
+            assert_eq!(Bridge::instant_finalize(Origin::signed(6), hash, 0), Err("Instant finalization disabled"));
+            assert_eq!(Balances::total_balance(&5), 100);
+        });
+    }
+
+    #[test]
+    fn instant_finalize_by_other_accounts_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert!(Bridge::set_instant_finalize(Origin::signed(1), true, 1).is_err());
+            assert_eq!(Bridge::instant_finalize_enabled(), false);
+            assert_ok!(Bridge::set_instant_finalize(Origin::ROOT, true, 6));
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_eq!(Bridge::instant_finalize(Origin::signed(1), hash, 0), Err("Invalid non-trusted relayer"));
+        });
+    }
+
+    #[test]
+    fn relayer_submit_count_should_track_extrinsic_senders() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(4, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(sign_deposit(1, 5, hash, quantity), Err("Invalid duplicate signings"));
+            assert_eq!(Bridge::relayer_submit_count(4), 1);
+            assert_eq!(Bridge::relayer_submit_count(1), 1);
+            assert_eq!(Bridge::relayer_submit_count(5), 0);
+        });
+    }
+
+    #[test]
+    fn deposits_paused_should_only_block_deposits() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(Bridge::set_deposits_paused(Origin::ROOT, true));
+            assert_eq!(deposit(5, 5, Blake2Hasher::hash(b"b sends money to a"), 10), Err("Deposits paused"));
+            assert_eq!(sign_deposit(1, 5, hash, 10), Err("Deposits paused"));
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_ok!(Bridge::set_deposits_paused(Origin::ROOT, false));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+        });
+    }
+
+    #[test]
+    fn withdraws_paused_should_only_block_withdraws() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(withdraw(5, 10, signed_tx));
+            let record_hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(Bridge::set_withdraws_paused(Origin::ROOT, true));
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Withdraws paused"));
+            assert_eq!(sign_withdraw(1, 5, record_hash, 10, signed_tx), Err("Withdraws paused"));
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+        });
+    }
+
+    #[test]
+    fn paused_should_block_both_directions() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert!(Bridge::set_paused(Origin::signed(1), true).is_err());
+            assert_ok!(Bridge::set_paused(Origin::ROOT, true));
+            assert_eq!(deposit(5, 5, hash, 10), Err("Bridge paused"));
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Bridge paused"));
+            assert_ok!(Bridge::set_paused(Origin::ROOT, false));
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(withdraw(5, 10, signed_tx));
+        });
+    }
+
+    #[test]
+    fn message_nonce_should_increment_across_directions() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::Withdraw(5, 10, 0)));
+
+            assert_ok!(deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::message_nonce(), 1);
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::DepositFinalized(5, hash, 0, 10, 1)));
+
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::Withdraw(5, 10, 2)));
+            assert_eq!(Bridge::message_nonce(), 3);
+        });
+    }
+
+    #[test]
+    fn withdraw_as_a_function_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_eq!(System::events(), vec![
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: Event::bridge(RawEvent::Withdraw(5, 10, 0)),
+                }]
+            );
+        });
+    }
+
+    #[test]
+    fn withdraw_with_not_enough_balance_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_eq!(Balances::total_balance(&4), 100);
+            assert_eq!(withdraw(4, 101, signed_tx), Err("Invalid balance for withdraw"));
+        });
+    }
+
+    #[test]
+    fn withdraw_outside_limits_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_eq!(Bridge::set_withdraw_limits(Origin::ROOT, 20, 10), Err("Invalid withdraw limits"));
+            assert_ok!(Bridge::set_withdraw_limits(Origin::ROOT, 10, 20));
+            assert_eq!(withdraw(5, 9, signed_tx), Err("Withdraw below minimum"));
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_ok!(withdraw(5, 20, signed_tx));
+            assert_eq!(withdraw(5, 21, signed_tx), Err("Withdraw above maximum"));
+        });
+    }
+
+    #[test]
+    fn withdraw_limits_should_be_skipped_when_unset() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(withdraw(5, 1, signed_tx));
+            assert_ok!(withdraw(1, 10000, signed_tx));
+            // A zero maximum only applies the minimum
+            assert_ok!(Bridge::set_withdraw_limits(Origin::ROOT, 5, 0));
+            assert_eq!(withdraw(5, 4, signed_tx), Err("Withdraw below minimum"));
+            assert_ok!(withdraw(2, 10000, signed_tx));
+        });
+    }
+
+    #[test]
+    fn withdraw_beyond_window_limit_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(Bridge::set_withdraw_window_limit(Origin::ROOT, 10, Some(2)));
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Too many withdraws in window"));
+            // Other accounts have their own allowance
+            assert_ok!(withdraw(6, 10, signed_tx));
+
+            // The allowance resets once the next window starts
+            System::set_block_number(9);
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Too many withdraws in window"));
+            System::set_block_number(10);
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_eq!(Bridge::withdraws_in_window(5), (10, 1));
+        });
+    }
+
+    #[test]
+    fn expired_withdraw_should_release_window_slot() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(Bridge::set_withdraw_window_limit(Origin::ROOT, 10, Some(1)));
+            assert_ok!(Bridge::withdraw(Origin::signed(5), 10, signed_tx.to_vec(), None, Some(3)));
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Too many withdraws in window"));
+
+            System::set_block_number(3);
+            <Bridge as OnFinalise<u64>>::on_finalise(3);
+            assert!(Bridge::withdraw_expired(Bridge::withdraw_record_hash(0)));
+            assert_eq!(Bridge::withdraws_in_window(5), (0, 0));
+            assert_ok!(withdraw(5, 10, signed_tx));
+
+            // A withdraw expiring after its window has passed leaves the new window's count alone
+            assert_ok!(Bridge::withdraw(Origin::signed(6), 10, signed_tx.to_vec(), None, Some(12)));
+            System::set_block_number(10);
+            assert_ok!(withdraw(6, 10, signed_tx));
+            System::set_block_number(12);
+            <Bridge as OnFinalise<u64>>::on_finalise(12);
+            assert!(Bridge::withdraw_expired(Bridge::withdraw_record_hash(2)));
+            assert_eq!(Bridge::withdraws_in_window(6), (10, 1));
+            assert_eq!(withdraw(6, 10, signed_tx), Err("Too many withdraws in window"));
+        });
+    }
+
+    #[test]
+    fn failed_withdraw_should_release_window_slot() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(Bridge::set_withdraw_window_limit(Origin::ROOT, 10, Some(1)));
+            assert_ok!(withdraw(5, 10, signed_tx));
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Too many withdraws in window"));
+
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(1), hash));
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(2), hash));
+            assert!(Bridge::withdraw_failed(hash));
+            assert_eq!(Bridge::withdraws_in_window(5), (0, 0));
+            assert_ok!(withdraw(5, 10, signed_tx));
+
+            // An approved withdraw keeps its slot
+            let hash = Bridge::withdraw_record_hash(1);
+            assert_ok!(sign_withdraw(1, 5, hash, 10, signed_tx));
+            assert_ok!(sign_withdraw(2, 5, hash, 10, signed_tx));
+            assert_eq!(Bridge::withdraw_counted_in_window(hash), None);
+            assert_eq!(Bridge::withdraws_in_window(5), (0, 1));
+        });
+    }
+
+    #[test]
+    fn set_withdraw_window_limit_should_validate() {
+        with_externalities(&mut new_test_ext(), || {
+            assert!(Bridge::set_withdraw_window_limit(Origin::signed(1), 10, Some(2)).is_err());
+            assert_eq!(Bridge::set_withdraw_window_limit(Origin::ROOT, 0, Some(2)), Err("Invalid zero window length"));
+            assert_ok!(Bridge::set_withdraw_window_limit(Origin::ROOT, 0, None));
+        });
+    }
+
+    #[test]
+    fn withdraw_with_reset_nonce_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(withdraw(5, 10, signed_tx));
+            let hash = Bridge::withdraw_record_hash(0);
+            // Simulate a faulty migration resetting the nonce
+            <bridge::WithdrawNonceOf<Test>>::insert(5, 0);
+            assert_eq!(withdraw(5, 10, signed_tx), Err("Withdraw hash collision"));
+            assert_eq!(Bridge::withdraw_count(), 1);
+            assert_eq!(Bridge::withdraw_of(hash).unwrap().index, 0);
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_supermajority_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100);
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100 - quantity);
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_once_complete_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100);
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100 - quantity);
+            assert_eq!(sign_withdraw(3, 5, hash, quantity, cross_chain_proof),
+                       Err("Transaction already completed"))
+        });
+    }
+    
+    #[test]
+    fn attest_withdraw_failure_should_refund_completed_withdraw() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
+            assert_eq!(Balances::total_balance(&5), 100 - quantity);
+
+            assert_eq!(Bridge::attest_withdraw_failure(Origin::signed(5), hash), Err("Invalid non-authority sender"));
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(1), hash));
+            assert_eq!(Bridge::attest_withdraw_failure(Origin::signed(1), hash), Err("Invalid duplicate signings"));
+            assert_eq!(Balances::total_balance(&5), 100 - quantity);
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(2), hash));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::WithdrawFailed(5, hash, quantity)));
+            assert_eq!(Bridge::attest_withdraw_failure(Origin::signed(3), hash), Err("Withdraw already failed"));
+        });
+    }
+
+    #[test]
+    fn attest_withdraw_failure_should_refund_to_refund_address() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(Bridge::withdraw(Origin::signed(5), quantity, cross_chain_proof.to_vec(), Some(6), None));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_eq!(Bridge::withdraw_refund_to(hash), Some(6));
+            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
+
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(1), hash));
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(2), hash));
+            assert_eq!(Balances::total_balance(&5), 100 - quantity);
+            assert_eq!(Balances::total_balance(&6), 100 + quantity);
+        });
+    }
+
+    #[test]
+    fn withdraw_should_expire_at_requested_block() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(Bridge::set_max_withdraw_expiry(Origin::ROOT, 100));
+            assert_eq!(Bridge::withdraw(Origin::signed(5), 10, cross_chain_proof.to_vec(), None, Some(1)),
+                       Err("Expiry must be in the future"));
+            assert_ok!(Bridge::withdraw(Origin::signed(5), 10, cross_chain_proof.to_vec(), None, Some(5)));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_eq!(Bridge::withdraw_reserved(5), 10);
+            assert_eq!(Bridge::withdraw_expires_at(hash), Some(5));
+            assert_ok!(withdraw(6, 10, cross_chain_proof));
+            assert_eq!(Bridge::withdraw_expires_at(Bridge::withdraw_record_hash(1)), None);
+
+            <Bridge as OnFinalise<u64>>::on_finalise(4);
+            assert!(!Bridge::withdraw_expired(hash));
+            <Bridge as OnFinalise<u64>>::on_finalise(5);
+            assert!(Bridge::withdraw_expired(hash));
+            assert_eq!(Bridge::withdraw_reserved(5), 0);
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::WithdrawExpired(5, hash, 10)));
+            assert_eq!(sign_withdraw(1, 5, hash, 10, cross_chain_proof), Err("Withdraw expired"));
+            assert_eq!(Balances::total_balance(&5), 100);
+        });
+    }
+
+    #[test]
+    fn withdraw_expiry_should_be_clamped_to_governance_max() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(Bridge::set_max_withdraw_expiry(Origin::ROOT, 10));
+            assert_ok!(Bridge::withdraw(Origin::signed(5), 10, cross_chain_proof.to_vec(), None, Some(1000)));
+            assert_eq!(Bridge::withdraws_expiring_at(11), vec![Bridge::withdraw_record_hash(0)]);
+            assert_eq!(Bridge::withdraw_expires_at(Bridge::withdraw_record_hash(0)), Some(11));
+
+            // Approved withdraws are not expired
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(sign_withdraw(1, 5, hash, 10, cross_chain_proof));
+            assert_ok!(sign_withdraw(2, 5, hash, 10, cross_chain_proof));
+            <Bridge as OnFinalise<u64>>::on_finalise(11);
+            assert!(!Bridge::withdraw_expired(hash));
+        });
+    }
+
+    #[test]
+    fn attest_withdraw_failure_should_cancel_pending_withdraw() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(1), hash));
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(2), hash));
+            assert!(Bridge::withdraw_failed(hash));
+            assert_eq!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof), Err("Withdraw failed"));
+            assert_eq!(Balances::total_balance(&5), 100);
+        });
+    }
+
+    #[test]
+    fn withdraw_reserved_should_track_pending_withdraws() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(withdraw(5, 10, cross_chain_proof));
+            let approved = Bridge::withdraw_record_hash(0);
+            assert_ok!(withdraw(5, 20, cross_chain_proof));
+            let failed = Bridge::withdraw_record_hash(1);
+            assert_eq!(Bridge::withdraw_reserved(5), 30);
+
+            assert_ok!(sign_withdraw(1, 5, approved, 10, cross_chain_proof));
+            assert_eq!(Bridge::withdraw_reserved(5), 30);
+            assert_ok!(sign_withdraw(2, 5, approved, 10, cross_chain_proof));
+            assert_eq!(Bridge::withdraw_reserved(5), 20);
+
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(1), failed));
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(2), failed));
+            assert_eq!(Bridge::withdraw_reserved(5), 0);
+
+            // Failing an already approved withdraw releases nothing further
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(1), approved));
+            assert_ok!(Bridge::attest_withdraw_failure(Origin::signed(2), approved));
+            assert_eq!(Bridge::withdraw_reserved(5), 0);
+        });
+    }
+
+    #[test]
+    fn confirm_withdraws_batch_should_skip_failed_items() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let proof = b"a sent b 1 ETH".to_vec();
+            assert_ok!(withdraw(5, 10, &proof));
+            assert_ok!(withdraw(6, 20, &proof));
+            let (a, b) = (Bridge::withdraw_record_hash(0), Bridge::withdraw_record_hash(1));
+            let unknown = Blake2Hasher::hash(b"no such withdraw");
+
+            assert_eq!(Bridge::confirm_withdraws_batch(Origin::signed(1), vec![]), Err("Invalid empty batch"));
+            assert_eq!(Bridge::confirm_withdraws_batch(Origin::signed(4), vec![(5, a, 10, proof.clone())]),
+                       Err("Invalid non-authority sender"));
+            let too_many = vec![(5, a, 10, proof.clone()); bridge::MAX_WITHDRAW_BATCH_SIZE + 1];
+            assert_eq!(Bridge::confirm_withdraws_batch(Origin::signed(1), too_many), Err("Batch too large"));
+
+            assert_ok!(Bridge::confirm_withdraws_batch(Origin::signed(1), vec![
+                (5, a, 10, proof.clone()),
+                (5, unknown, 10, proof.clone()),
+                (6, b, 20, proof.clone()),
+            ]));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::WithdrawSignatureFailed(1, unknown, b"Invalid record hash".to_vec())));
+            assert_eq!(Bridge::withdraw_of(a).unwrap().signers, vec![(1, proof.clone())]);
+            assert_eq!(Bridge::withdraw_of(b).unwrap().signers, vec![(1, proof.clone())]);
+            assert_eq!(Bridge::relayer_submit_count(1), 1);
+
+            // Signing again approves the valid items and skips the duplicate
+            assert_ok!(Bridge::confirm_withdraws_batch(Origin::signed(2), vec![
+                (5, a, 10, proof.clone()),
+                (6, b, 20, proof.clone()),
+            ]));
+            assert_ok!(Bridge::confirm_withdraws_batch(Origin::signed(2), vec![(5, a, 10, proof.clone())]));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::WithdrawSignatureFailed(2, a, b"Transaction already completed".to_vec())));
+            assert!(Bridge::withdraw_of(a).unwrap().completed);
+            assert!(Bridge::withdraw_of(b).unwrap().completed);
+            assert_eq!(Balances::total_balance(&5), 90);
+            assert_eq!(Balances::total_balance(&6), 80);
+        });
+    }
+
+    #[test]
+    fn withdraw_reserved_should_saturate() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            <bridge::WithdrawReserved<Test>>::insert(5, u64::max_value() - 5);
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            assert_eq!(Bridge::withdraw_reserved(5), u64::max_value());
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_with_wrong_quantity_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_eq!(sign_withdraw(1, 5, hash, quantity - 1, cross_chain_proof), Err("Quantities don't match"));
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_with_wrong_target_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_eq!(sign_withdraw(1, 4, hash, quantity, cross_chain_proof), Err("Accounts do not match"));
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_as_non_authority_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_eq!(sign_withdraw(5, 5, hash, quantity, cross_chain_proof), Err("Invalid non-authority sender"));
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_twice_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0);
+            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            assert_eq!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof), Err("Invalid duplicate signings"))
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_with_non_existent_record_hash_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            let hash = Blake2Hasher::hash(b"drew stone was here");
+            assert_eq!(sign_withdraw(1, 4, hash, quantity, cross_chain_proof), Err("Invalid record hash"));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_below_existential_deposit_should_not_work() {
+        with_externalities(&mut new_test_ext_with_config(vec![(1, 10000), (2, 10000), (3, 10000)], 50), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(1, 7, hash, quantity));
+            assert_eq!(Bridge::simulate_deposit(7, hash, 0, quantity, 2), bridge::DepositSimOutcome::Reject);
+            assert_ok!(sign_deposit(2, 7, hash, quantity));
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().completed, false);
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().signers, vec![1, 2]);
+            assert!(Bridge::deposit_rejected((hash, 0)));
+            assert_eq!(System::events().last().unwrap().event,
+                       Event::bridge(RawEvent::DepositRejected(hash, 0)));
+            assert_eq!(Balances::total_balance(&7), 0);
+            assert_eq!(sign_deposit(3, 7, hash, quantity), Err("Deposit rejected"));
+            let rejected = hash;
+            assert!(Bridge::release_rejected_deposit(Origin::signed(1), rejected, 0).is_err());
+            assert_eq!(Bridge::release_rejected_deposit(Origin::ROOT, rejected, 0), Err("Below existential deposit"));
+
+            // Deposits large enough to create the account are credited as usual
+            let hash = Blake2Hasher::hash(b"a sends more money to b");
+            assert_ok!(deposit(1, 7, hash, 50));
+            assert_ok!(sign_deposit(2, 7, hash, 50));
+            assert_eq!(Balances::total_balance(&7), 50);
+
+            // Once the account exists governance can credit the rejected deposit
+            assert_ok!(Bridge::release_rejected_deposit(Origin::ROOT, rejected, 0));
+            assert_eq!(Balances::total_balance(&7), 50 + quantity);
+            assert!(Bridge::deposit_of((rejected, 0)).unwrap().completed);
+            assert_eq!(Bridge::release_rejected_deposit(Origin::ROOT, rejected, 0), Err("Deposit not rejected"));
+        });
+    }
+
+    #[test]
+    fn instant_finalized_rejected_deposit_should_not_be_released_again() {
+        with_externalities(&mut new_test_ext_with_config(vec![(1, 10000), (2, 10000), (3, 10000)], 50), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(1, 7, hash, 10));
+            assert_ok!(sign_deposit(2, 7, hash, 10));
+            assert!(Bridge::deposit_rejected((hash, 0)));
+
+            // Once the target is funded the trusted relayer finalizes the deposit itself
+            assert_ok!(Bridge::force_credit(Origin::ROOT, 7, 50, b"fund target".to_vec()));
+            assert_ok!(Bridge::set_instant_finalize(Origin::ROOT, true, 6));
+            assert_ok!(Bridge::instant_finalize(Origin::signed(6), hash, 0));
+            assert_eq!(Balances::total_balance(&7), 60);
+            assert!(!Bridge::deposit_rejected((hash, 0)));
+
+            // Governance cannot credit it a second time
+            assert_eq!(Bridge::release_rejected_deposit(Origin::ROOT, hash, 0), Err("Deposit not rejected"));
+            <bridge::DepositRejected<Test>>::insert((hash, 0), true);
+            assert_eq!(Bridge::release_rejected_deposit(Origin::ROOT, hash, 0), Err("Transaction already completed"));
+            assert_eq!(Balances::total_balance(&7), 60);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_overflowing_escrow_should_be_rejected() {
+        with_externalities(&mut new_test_ext_with_config(vec![(1, 10000), (2, 10000), (3, 10000)], 50), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(Bridge::set_escrow_below_existential(Origin::ROOT, true));
+            <bridge::EscrowedDepositOf<Test>>::insert(7, u64::max_value());
+            assert_ok!(deposit(1, 7, hash, 10));
+            assert_ok!(sign_deposit(2, 7, hash, 10));
+            assert!(Bridge::deposit_rejected((hash, 0)));
+            assert_eq!(Bridge::escrowed_deposit_of(7), u64::max_value());
+            assert_eq!(Bridge::release_rejected_deposit(Origin::ROOT, hash, 0), Err("Escrowed deposit overflow"));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_below_existential_deposit_should_escrow() {
+        with_externalities(&mut new_test_ext_with_config(vec![(1, 10000), (2, 10000), (3, 10000)], 50), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(Bridge::set_escrow_below_existential(Origin::ROOT, true));
+            assert_ok!(deposit(1, 7, hash, quantity));
+            assert_ok!(sign_deposit(2, 7, hash, quantity));
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().completed, true);
+            assert_eq!(Bridge::escrowed_deposit_of(7), quantity);
+            assert_eq!(Balances::total_balance(&7), 0);
+
+            // The escrow is released once the account has been topped up
+            assert_eq!(Bridge::claim_escrowed_deposit(Origin::signed(7)), Err("Below existential deposit"));
+            Balances::increase_free_balance_creating(&7, 60);
+            assert_ok!(Bridge::claim_escrowed_deposit(Origin::signed(7)));
+            assert_eq!(Balances::total_balance(&7), 60 + quantity);
+            assert_eq!(Bridge::escrowed_deposit_of(7), 0);
+            assert_eq!(Bridge::claim_escrowed_deposit(Origin::signed(7)), Err("No escrowed deposit"));
+        });
+    }
+
+    #[test]
+    fn approval_decay_should_reduce_late_signer_stake() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let key = (hash, 0);
+            assert_eq!(Bridge::set_approval_decay(Origin::ROOT, 10, 1001), Err("Invalid decay above 100%"));
+            assert_ok!(Bridge::set_approval_decay(Origin::ROOT, 10, 500));
+            // The depositing authority signs at creation
+            assert_ok!(deposit(1, 5, hash, 10));
+            System::set_block_number(21);
+            assert_eq!(Bridge::effective_stake(&key, &1), 10000);
+
+            // Without decay these two signers would hold a supermajority, and decay
+            // is capped however late the signer is
+            System::set_block_number(101);
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_eq!(Bridge::effective_stake(&key, &2), 5000);
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            System::set_block_number(111);
+            assert_ok!(sign_deposit(3, 5, hash, 10));
+            assert_eq!(Bridge::effective_stake(&key, &3), 5000);
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn approval_decay_should_be_off_by_default() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            System::set_block_number(50);
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::effective_stake(&(hash, 0), &1), 10000);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_zero_total_stake_should_not_finalize() {
+        with_externalities(&mut new_test_ext_with_balances(vec![]), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_eq!(Balances::total_issuance(), 0);
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_ok!(sign_deposit(3, 5, hash, quantity));
+            assert_eq!(Bridge::deposit_of((hash, 0)).unwrap().completed, false);
+            assert_eq!(Balances::total_balance(&5), 0);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_count_threshold_should_ignore_stake() {
+        with_externalities(&mut new_test_ext_with_balances(vec![(1, 1), (2, 1), (3, 1), (5, 10000)]), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(Bridge::set_count_threshold(Origin::ROOT, Some(2)));
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 10000);
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 10000 + quantity);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_below_count_threshold_should_not_finalize() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(Bridge::set_count_threshold(Origin::ROOT, Some(3)));
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(3, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn set_count_threshold_should_validate() {
+        with_externalities(&mut new_test_ext(), || {
+            assert!(Bridge::set_count_threshold(Origin::signed(1), Some(2)).is_err());
+            assert_eq!(Bridge::set_count_threshold(Origin::ROOT, Some(0)), Err("Invalid zero count threshold"));
+            assert_ok!(Bridge::set_count_threshold(Origin::ROOT, Some(2)));
+            assert_eq!(Bridge::count_threshold(), Some(2));
+            assert_ok!(Bridge::set_count_threshold(Origin::ROOT, None));
+            assert_eq!(Bridge::count_threshold(), None);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_finalizes_iff_signers_hold_supermajority() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..200 {
+            // Occasionally use huge stakes to exercise the overflow-free comparison
+            let bound = if rng.next() % 4 == 0 { u64::max_value() / 8 } else { 10000 };
+            let stakes: Vec<u64> = (0..3).map(|_| rng.next() % bound).collect();
+            let others = 1 + rng.next() % bound;
+
+            // Pick a random ordered subset of the authorities to sign
+            let mut signers = vec![1u64, 2, 3];
+            for i in (1..signers.len()).rev() {
+                let j = (rng.next() % (i as u64 + 1)) as usize;
+                signers.swap(i, j);
+            }
+            signers.truncate((rng.next() % 4) as usize);
+
+            let balances = vec![(1, stakes[0]), (2, stakes[1]), (3, stakes[2]), (5, others)];
+            with_externalities(&mut new_test_ext_with_balances(balances), || {
+                System::set_block_number(1);
+                let hash = Blake2Hasher::hash(b"a sends money to b");
+                let quantity = 10;
+                let total = stakes.iter()
+                    .fold(Some(others), |acc, s| acc.and_then(|a| a.checked_add(*s)))
+                    .expect("total issuance should not overflow");
+                assert_eq!(Balances::total_issuance(), total);
+                assert_ok!(deposit(5, 5, hash, quantity));
+
+                let mut stake_sum = 0u64;
+                let mut credited = false;
+                for s in signers.iter() {
+                    if credited {
+                        assert_eq!(sign_deposit(*s, 5, hash, quantity), Err("Transaction already completed"));
+                        continue;
+                    }
+                    assert_ok!(sign_deposit(*s, 5, hash, quantity));
+                    stake_sum = stake_sum.checked_add(stakes[(*s - 1) as usize])
+                        .expect("stake sum should not overflow");
+                    let summed = Bridge::sum_stakes(signers.iter().take_while(|t| *t != s).chain(Some(s))
+                        .map(|t| stakes[(*t - 1) as usize]));
+                    assert_eq!(summed, stake_sum);
+                    let completed = Bridge::deposit_of((hash, 0)).unwrap().completed;
+                    assert_eq!(completed, stake_sum > total - stake_sum);
+                    credited = completed;
+                }
+
+                // The target is credited exactly once, and only on finalization
+                let expected = if credited { others + quantity } else { others };
+                assert_eq!(Balances::total_balance(&5), expected);
+            });
+        }
+    }
+
+    #[test]
+    fn sum_stakes_should_saturate_instead_of_wrapping() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_eq!(Bridge::sum_stakes(vec![1, 2, 3].into_iter()), 6);
+            assert_eq!(Bridge::sum_stakes(vec![u64::max_value() - 1, 5].into_iter()), u64::max_value());
+            assert_eq!(Bridge::sum_stakes(vec![u64::max_value(), u64::max_value(), 1].into_iter()), u64::max_value());
+        });
+    }
+
+    // Harness for storage migrations: each migration gets helpers writing the legacy
+    // layout it upgrades from, and tests asserting the new layout and that running
+    // it a second time changes nothing.
+    mod migration_tests {
+        use super::*;
+        use runtime_support::storage;
+        use runtime_support::storage::generator;
+
+        type LegacyDepositRecord = (bridge::DepositIndex, u64, u64, Vec<u64>, bool);
+        type LegacyWithdrawRecord = (bridge::WithdrawIndex, u64, u64, Vec<(u64, Vec<u8>)>, bool);
+
+        fn legacy_deposit_key(hash: &H256) -> Vec<u8> {
+            let mut key = <bridge::DepositOf<Test> as generator::StorageMap<(H256, u32), bridge::DepositRecord<u64, u64>>>::prefix().to_vec();
+            hash.encode_to(&mut key);
+            key
+        }
+
+        // Writes deposits as they were stored before being keyed by log index, on a
+        // chain that has not migrated yet
+        fn put_legacy_deposits(deposits: &[(H256, LegacyDepositRecord)]) {
+            <bridge::DepositKeysMigrated<Test>>::kill();
+            let deposits_key = <bridge::Deposits<Test> as generator::StorageValue<Vec<(H256, u32)>>>::key();
+            let hashes = deposits.iter().map(|d| d.0).collect::<Vec<_>>();
+            storage::put(deposits_key, &hashes);
+            for (hash, record) in deposits {
+                storage::put(&legacy_deposit_key(hash), record);
+            }
+        }
+
+        #[test]
+        fn record_tuples_should_decode_as_structs() {
+            let deposit: LegacyDepositRecord = (0, 5, 10, vec![1, 2], true);
+            let record: bridge::DepositRecord<u64, u64> = Decode::decode(&mut &deposit.encode()[..]).unwrap();
+            assert_eq!(record, bridge::DepositRecord { index: 0, target: 5, quantity: 10, signers: vec![1, 2], completed: true });
+            assert_eq!(record.encode(), deposit.encode());
+
+            let withdraw: LegacyWithdrawRecord = (3, 5, 10, vec![(1, b"proof".to_vec())], false);
+            let record: bridge::WithdrawRecord<u64, u64> = Decode::decode(&mut &withdraw.encode()[..]).unwrap();
+            assert_eq!(record, bridge::WithdrawRecord { index: 3, target: 5, quantity: 10, signers: vec![(1, b"proof".to_vec())], completed: false });
+            assert_eq!(record.encode(), withdraw.encode());
+        }
+
+        #[test]
+        fn deposit_keys_should_migrate_to_log_index_zero() {
+            with_externalities(&mut new_test_ext(), || {
+                System::set_block_number(1);
+                let (a, b) = (Blake2Hasher::hash(b"a"), Blake2Hasher::hash(b"b"));
+                put_legacy_deposits(&[(a, (0, 5, 10, vec![1], false)), (b, (1, 6, 20, vec![], true))]);
+
+                <Bridge as OnFinalise<u64>>::on_finalise(1);
+                assert!(Bridge::deposit_keys_migrated());
+                assert_eq!(Bridge::deposits(), vec![(a, 0), (b, 0)]);
+                assert_eq!(Bridge::deposit_of((a, 0)), Some(bridge::DepositRecord { index: 0, target: 5, quantity: 10, signers: vec![1], completed: false }));
+                assert_eq!(Bridge::deposit_of((b, 0)).unwrap().completed, true);
+                assert!(!storage::exists(&legacy_deposit_key(&a)));
+                assert!(!storage::exists(&legacy_deposit_key(&b)));
+
+                // Migrated records can be signed as usual
+                assert_ok!(sign_deposit(2, 5, a, 10));
+                assert_eq!(Balances::total_balance(&5), 110);
+            });
+        }
+
+        #[test]
+        fn deposit_key_migration_should_be_idempotent() {
+            with_externalities(&mut new_test_ext(), || {
+                System::set_block_number(1);
+                let hash = Blake2Hasher::hash(b"a");
+                put_legacy_deposits(&[(hash, (0, 5, 10, vec![1], false))]);
+
+                Bridge::migrate_deposit_keys();
+                let deposits = Bridge::deposits();
+                let record = Bridge::deposit_of((hash, 0));
+                Bridge::migrate_deposit_keys();
+                assert_eq!(Bridge::deposits(), deposits);
+                assert_eq!(Bridge::deposit_of((hash, 0)), record);
+            });
+        }
+
+        #[test]
+        fn deposit_key_migration_on_fresh_storage_should_be_a_no_op() {
+            with_externalities(&mut new_test_ext(), || {
+                <bridge::DepositKeysMigrated<Test>>::kill();
+                Bridge::migrate_deposit_keys();
+                assert!(Bridge::deposit_keys_migrated());
+                assert_eq!(Bridge::deposits(), vec![]);
+            });
+        }
+
+        #[test]
+        fn deposit_keys_should_not_need_migrating_on_a_new_chain() {
+            with_externalities(&mut new_test_ext(), || {
+                System::set_block_number(1);
+                assert!(Bridge::deposit_keys_migrated());
+                let hash = Blake2Hasher::hash(b"a");
+                assert_ok!(deposit(5, 5, hash, 10));
+                <Bridge as OnFinalise<u64>>::on_finalise(1);
+                assert_eq!(Bridge::deposits(), vec![(hash, 0)]);
+                assert!(Bridge::deposit_of((hash, 0)).is_some());
+            });
+        }
     }
 }